@@ -0,0 +1,284 @@
+//! Tables of runs and realms, for spreadsheets and databases.
+
+use std::collections::HashSet;
+use std::io;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::types::{Realm, Run};
+
+/// The stat categories, as they're named in the `category` column.
+pub const CATEGORIES : [&str; 5] = ["spell_casts", "damage_to_enemies", "damage_to_wizard", "items_used", "purchases"];
+
+/// A row of the runs table.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunRow<'a>
+{
+    /// the run's id, which the stats rows refer to
+    pub run : &'a str,
+    pub name : &'a str,
+    pub outcome : String,
+    pub game_mode : String,
+    pub realms : i64,
+    pub turns : i64,
+}
+
+impl<'a> RunRow<'a> {
+    pub fn new(run : &'a Run) -> RunRow<'a>
+    {
+        RunRow {
+            run : &run.id,
+            name : &run.name,
+            outcome : format!("{:?}", run.outcome),
+            game_mode : run.game_mode.to_string(),
+            realms : run.realms,
+            turns : run.turns_taken_run,
+        }
+    }
+}
+
+/// A row of the stats table: one number from one realm of one run.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatRow<'a>
+{
+    pub run : &'a str,
+    pub realm : i64,
+    pub category : &'static str,
+    pub key : &'a str,
+    pub value : usize,
+}
+
+/// Every number in `realm` as a row, category by category, biggest first within each.
+/// A purchase is a row with a value of 1.
+pub fn stat_rows<'a>(run : &'a Run, realm : &'a Realm) -> Vec<StatRow<'a>>
+{
+    let maps = [&realm.spell_casts, &realm.damage_to_enemies, &realm.damage_to_wizard, &realm.items_used];
+    let mut rows = Vec::new();
+
+    for (category, map) in CATEGORIES.iter().zip(maps.iter()) {
+        let mut entries : Vec<_> = map.iter().flat_map(|map| map.iter()).collect();
+        entries.sort_by(|a, b| { b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)) });
+
+        for (key, value) in entries {
+            rows.push(StatRow { run : &run.id, realm : realm.realm_number, category, key, value : *value });
+        }
+    }
+
+    let mut purchases : Vec<_> = realm.purchases.iter().flatten().collect();
+    purchases.sort();
+
+    for name in purchases {
+        rows.push(StatRow { run : &run.id, realm : realm.realm_number, category : "purchases", key : name, value : 1 });
+    }
+
+    rows
+}
+
+/// Ids that more than one of `runs` has, in the order they first repeat. Every table here is keyed
+/// by run id, so a repeated one would have its rows mixed up or replaced; this only happens
+/// when the same save folder is read twice.
+pub fn duplicate_ids<'a, I>(runs : I) -> Vec<&'a str>
+    where I : IntoIterator<Item = &'a Run>
+{
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+
+    for run in runs {
+        if !seen.insert(run.id.as_str()) && !duplicates.contains(&run.id.as_str()) {
+            duplicates.push(run.id.as_str());
+        }
+    }
+
+    duplicates
+}
+
+/// Writes the runs table as CSV: `run,name,outcome,game_mode,realms,turns`, with a header row.
+pub fn write_runs_csv<'a, W, I>(runs : I, writer : W) -> csv::Result<()>
+    where W : io::Write, I : IntoIterator<Item = &'a Run>
+{
+    // headers written by hand so there's still one when there are no runs
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(writer);
+    writer.write_record(["run", "name", "outcome", "game_mode", "realms", "turns"])?;
+
+    for run in runs {
+        writer.serialize(RunRow::new(run))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the stats table as CSV, in tidy long form: `run,realm,category,key,value`, with a header row.
+pub fn write_stats_csv<'a, W, I>(runs : I, writer : W) -> csv::Result<()>
+    where W : io::Write, I : IntoIterator<Item = &'a Run>
+{
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(writer);
+    writer.write_record(["run", "realm", "category", "key", "value"])?;
+
+    for run in runs {
+        for realm in run.realm_stats.iter().flatten() {
+            for row in stat_rows(run, realm) {
+                writer.serialize(row)?;
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+const SQLITE_SCHEMA : &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        run TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        outcome TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        realms INTEGER NOT NULL,
+        turns INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS realms (
+        run TEXT NOT NULL REFERENCES runs(run),
+        realm INTEGER NOT NULL,
+        outcome TEXT NOT NULL,
+        turns_realm INTEGER NOT NULL,
+        turns_run INTEGER NOT NULL,
+        PRIMARY KEY (run, realm)
+    );
+    CREATE TABLE IF NOT EXISTS stat_entries (
+        run TEXT NOT NULL,
+        realm INTEGER NOT NULL,
+        category TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value INTEGER NOT NULL,
+        PRIMARY KEY (run, realm, category, key),
+        FOREIGN KEY (run, realm) REFERENCES realms(run, realm)
+    );
+    CREATE TABLE IF NOT EXISTS purchases (
+        run TEXT NOT NULL,
+        realm INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        PRIMARY KEY (run, realm, name),
+        FOREIGN KEY (run, realm) REFERENCES realms(run, realm)
+    );
+";
+
+/// Writes `runs` into the `runs`, `realms`, `stat_entries` and `purchases` tables of a SQLite database,
+/// creating them if they don't exist yet.
+///
+/// A run that's already in the database is replaced, realms and all, so exporting a save folder again
+/// after more runs have been played leaves one up to date copy of each. Runs that are in the database
+/// but not in `runs` are left alone.
+///
+/// Returns how many rows of the runs table were written, which is fewer than the runs given if
+/// any share an id, as the later one replaces the earlier; see `duplicate_ids`.
+pub fn write_sqlite<'a, I>(runs : I, connection : &mut Connection) -> rusqlite::Result<usize>
+    where I : IntoIterator<Item = &'a Run>
+{
+    connection.execute_batch(SQLITE_SCHEMA)?;
+
+    // all or nothing, and much faster than a transaction per row
+    let transaction = connection.transaction()?;
+    let mut written = HashSet::new();
+
+    for run in runs {
+        written.insert(run.id.as_str());
+
+        let row = RunRow::new(run);
+        transaction.execute(
+            "INSERT INTO runs (run, name, outcome, game_mode, realms, turns) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (run) DO UPDATE SET name = excluded.name, outcome = excluded.outcome, game_mode = excluded.game_mode,
+                realms = excluded.realms, turns = excluded.turns",
+            params![row.run, row.name, row.outcome, row.game_mode, row.realms, row.turns],
+        )?;
+
+        // the run may have more realms than last time, and a realm's stats can't be matched up row by row
+        for table in ["purchases", "stat_entries", "realms"].iter() {
+            transaction.execute(&format!("DELETE FROM {} WHERE run = ?1", table), params![run.id])?;
+        }
+
+        for realm in run.realm_stats.iter().flatten() {
+            transaction.execute(
+                "INSERT INTO realms (run, realm, outcome, turns_realm, turns_run) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![run.id, realm.realm_number, format!("{:?}", realm.outcome), realm.turns_taken_realm, realm.turns_taken_run],
+            )?;
+
+            for row in stat_rows(run, realm) {
+                if row.category == "purchases" {
+                    transaction.execute(
+                        "INSERT INTO purchases (run, realm, name) VALUES (?1, ?2, ?3)",
+                        params![row.run, row.realm, row.key],
+                    )?;
+                } else {
+                    transaction.execute(
+                        "INSERT INTO stat_entries (run, realm, category, key, value) VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![row.run, row.realm, row.category, row.key, row.value as i64],
+                    )?;
+                }
+            }
+        }
+    }
+
+    transaction.commit()?;
+
+    Ok(written.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::load::generate_run_report;
+    use crate::types::Outcome;
+
+    fn run(id : &str, realms : i64) -> Run
+    {
+        let mut run = generate_run_report((1..=realms).map(|realm_number| Realm {
+            realm_number,
+            outcome : Outcome::Victory,
+            spell_casts : Some(HashMap::from([("Fireball".to_string(), 2)])),
+            purchases : Some(std::iter::once("Fireball".to_string()).collect()),
+            ..Default::default()
+        }).collect());
+
+        run.id = format!("/saves/{}", id);
+        run.name = id.to_string();
+        run
+    }
+
+    fn count(connection : &Connection, sql : &str) -> i64
+    {
+        connection.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn exporting_again_replaces_runs_and_keeps_the_rest()
+    {
+        let mut connection = Connection::open_in_memory().unwrap();
+
+        assert_eq!(write_sqlite(&[run("1", 3), run("2", 2)], &mut connection).unwrap(), 2);
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM realms"), 5);
+
+        // run 1 got further, run 2 is no longer in the save folder and run 3 is new
+        assert_eq!(write_sqlite(&[run("1", 5), run("3", 1)], &mut connection).unwrap(), 2);
+
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM runs"), 3);
+        assert_eq!(count(&connection, "SELECT realms FROM runs WHERE name = '1'"), 5);
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM realms WHERE run = '/saves/1'"), 5);
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM stat_entries WHERE run = '/saves/1'"), 5);
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM purchases WHERE run = '/saves/1'"), 5);
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM realms WHERE run = '/saves/2'"), 2);
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM realms"), 8);
+    }
+
+    #[test]
+    fn runs_sharing_an_id_count_once()
+    {
+        let mut connection = Connection::open_in_memory().unwrap();
+
+        assert_eq!(write_sqlite(&[run("1", 2), run("1", 4)], &mut connection).unwrap(), 1);
+        assert_eq!(count(&connection, "SELECT realms FROM runs"), 4);
+        assert_eq!(duplicate_ids(&[run("1", 2), run("1", 4), run("2", 1)]), vec!["/saves/1"]);
+    }
+}
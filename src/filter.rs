@@ -0,0 +1,166 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::query::Query;
+use crate::types::{GameMode, Outcome, Run};
+
+/// Which runs go into the reports. Every condition that is set has to hold;
+/// the default has none set and lets every run through.
+#[derive(Default, Debug, Clone)]
+pub struct RunFilter
+{
+    /// any of these, or any outcome if empty
+    pub outcomes : Vec<Outcome>,
+    pub min_realm : Option<i64>,
+    pub max_realm : Option<i64>,
+    /// any of these, or any mode if empty, see `mode_matches`
+    pub game_modes : Vec<String>,
+    /// the range of run names, which the game numbers; a run with a name that isn't a number is left out when either is set.
+    /// Runs with the same number in different save folders are all in range
+    pub min_id : Option<u64>,
+    pub max_id : Option<u64>,
+    /// last played at or after this, going by the stats files' modification times
+    pub since : Option<SystemTime>,
+    /// last played before this
+    pub until : Option<SystemTime>,
+    /// bought all of these, by name, ignoring case
+    pub purchased : Vec<String>,
+    /// cast all of these at least once, by name, ignoring case
+    pub cast : Vec<String>,
+    /// an expression the run has to match, see the `query` module
+    pub query : Option<Query>,
+}
+
+impl RunFilter {
+    pub fn matches(&self, run : &Run) -> bool
+    {
+        if !self.outcomes.is_empty() && !self.outcomes.contains(&run.outcome) {
+            return false;
+        }
+
+        if self.min_realm.is_some_and(|min| run.realms < min) || self.max_realm.is_some_and(|max| run.realms > max) {
+            return false;
+        }
+
+        if !self.game_modes.is_empty() && !self.game_modes.iter().any(|mode| mode_matches(mode, &run.game_mode)) {
+            return false;
+        }
+
+        if self.min_id.is_some() || self.max_id.is_some() {
+            let number = match run.name.parse::<u64>() {
+                Ok(number) => number,
+                Err(_) => return false,
+            };
+
+            if self.min_id.is_some_and(|min| number < min) || self.max_id.is_some_and(|max| number > max) {
+                return false;
+            }
+        }
+
+        if self.since.is_some() || self.until.is_some() {
+            let last_played = match run.last_played {
+                Some(last_played) => last_played,
+                None => return false,
+            };
+
+            if self.since.is_some_and(|since| last_played < since) || self.until.is_some_and(|until| last_played >= until) {
+                return false;
+            }
+        }
+
+        let bought = |name : &String| run.purchases.iter().flatten().any(|purchase| purchase.name.eq_ignore_ascii_case(name));
+        if !self.purchased.iter().all(bought) {
+            return false;
+        }
+
+        let cast = |name : &String| run.spell_casts.iter().flatten().any(|(spell, count)| *count > 0 && spell.eq_ignore_ascii_case(name));
+        if !self.cast.iter().all(cast) {
+            return false;
+        }
+
+        self.query.as_ref().is_none_or(|query| query.matches(run))
+    }
+}
+
+/// Whether `mode` picks out `game_mode`, ignoring case: either a kind of mode (`normal`, `trial`
+/// or `weekly`) or a mode as it's displayed, e.g. `Trial: Fire and Ice`.
+pub fn mode_matches(mode : &str, game_mode : &GameMode) -> bool
+{
+    let kind = match game_mode {
+        GameMode::Normal => "normal",
+        GameMode::Trial(_) => "trial",
+        GameMode::Weekly(_) => "weekly",
+    };
+
+    mode.eq_ignore_ascii_case(kind) || mode.eq_ignore_ascii_case(&game_mode.to_string())
+}
+
+/// Midnight UTC at the start of a `YYYY-MM-DD` date, or `None` if it isn't one.
+pub fn parse_date(date : &str) -> Option<SystemTime>
+{
+    let mut parts = date.splitn(3, '-');
+    let year : i64 = parts.next()?.parse().ok()?;
+    let month : i64 = parts.next()?.parse().ok()?;
+    let day : i64 = parts.next()?.parse().ok()?;
+
+    let days_in_month = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+
+    if day < 1 || day > days_in_month {
+        return None;
+    }
+
+    // days since 1970-01-01, counting years from March so the leap day comes last
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    if days < 0 {
+        return None;
+    }
+
+    Some(UNIX_EPOCH + Duration::from_secs(days as u64 * 86400))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(date : &str) -> Option<u64>
+    {
+        parse_date(date).map(|time| time.duration_since(UNIX_EPOCH).unwrap().as_secs())
+    }
+
+    #[test]
+    fn dates_are_midnight_utc()
+    {
+        assert_eq!(seconds("1970-01-01"), Some(0));
+        assert_eq!(seconds("2000-03-01"), Some(951868800));
+        assert_eq!(seconds("2026-10-18"), Some(1792281600));
+        assert_eq!(seconds("2024-1-5"), Some(1704412800));
+    }
+
+    #[test]
+    fn leap_days_only_in_leap_years()
+    {
+        assert_eq!(seconds("2024-02-29"), Some(1709164800));
+        assert_eq!(seconds("2000-02-29"), Some(951782400));
+        assert_eq!(seconds("2023-02-29"), None);
+        assert_eq!(seconds("1900-02-29"), None);
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_date()
+    {
+        for date in ["", "2024", "2024-06", "2024-13-01", "2024-00-10", "2024-04-31", "2024-06-00", "2024-06-1x", "24/06/2024", "1969-12-31"] {
+            assert_eq!(parse_date(date), None, "{}", date);
+        }
+    }
+}
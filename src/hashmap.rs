@@ -49,3 +49,23 @@ pub fn lazy_init<T, V>(map : Option<HashMap<T, V>>) -> HashMap<T, V>
     map.or_else(|| {Some(HashMap::new())}).unwrap()
 }
 
+/// `merge_add` for maps of maps, merging the inner maps key by key.
+pub fn merge_add_nested<T, U, V>(map : Option<HashMap<T, HashMap<U, V>>>, other : Option<HashMap<T, HashMap<U, V>>>) -> Option<HashMap<T, HashMap<U, V>>>
+    where T : Eq + std::hash::Hash,
+        U : Eq + std::hash::Hash + Clone,
+        V : Copy + std::ops::Add<Output = V>
+{
+    match other {
+        Some(other) => {
+            let mut map = lazy_init(map);
+            
+            for (key, inner) in other {
+                let merged = map.remove(&key).merge_add(Some(inner));
+                map.insert(key, lazy_init(merged));
+            }
+            
+            Some(map)
+        },
+        None => map
+    }
+}
@@ -0,0 +1,262 @@
+//! A single self-contained HTML page of the summary, with inline SVG charts and no external assets.
+
+use riftwizardstats::MergedRuns;
+use riftwizardstats::report::{damage_by_realm, deaths_by_realm};
+
+use crate::summary::{summaries, Section, Summary, TopList};
+use crate::text::{top_keys, ReportOptions};
+
+use std::fmt::Write;
+
+const STYLE : &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h1, h2 { border-bottom: 1px solid #ccc; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { padding: 0.2em 0.8em; text-align: left; }
+td.n, th.n { text-align: right; }
+tr:nth-child(even) { background: #f4f4f4; }
+.charts svg { display: block; margin: 1em 0; }
+svg text { font-size: 12px; fill: #222; }
+";
+
+const VICTORY_COLOR : &str = "#4caf50";
+const DEFEAT_COLOR : &str = "#e53935";
+const ABANDONED_COLOR : &str = "#9e9e9e";
+const UNKNOWN_COLOR : &str = "#d0d0d0";
+const DEALT_COLOR : &str = "#1e88e5";
+const TAKEN_COLOR : &str = "#e53935";
+
+pub fn print_merged_runs_html(merged_runs : MergedRuns, report_options : &ReportOptions)
+{
+    // writing to a String can't fail, so the fmt::Results are ignored throughout
+    let mut page = String::new();
+
+    let _ = writeln!(page, "<!DOCTYPE html>");
+    let _ = writeln!(page, "<html><head><meta charset=\"utf-8\"><title>Rift Wizard Stats</title><style>{}</style></head><body>", STYLE);
+    let _ = writeln!(page, "<h1>Rift Wizard Stats</h1>");
+
+    write_charts(&mut page, &merged_runs, report_options);
+
+    for summary in summaries(merged_runs, report_options) {
+        write_summary(&mut page, &summary);
+    }
+
+    let _ = writeln!(page, "</body></html>");
+
+    print!("{}", page);
+}
+
+fn write_charts(page : &mut String, merged_runs : &MergedRuns, report_options : &ReportOptions)
+{
+    let _ = writeln!(page, "<section class=\"charts\">");
+
+    let _ = writeln!(page, "<h2>Outcomes</h2>");
+    write_pie_chart(page, &[
+        ("won", merged_runs.num_victory, VICTORY_COLOR),
+        ("lost", merged_runs.num_defeat, DEFEAT_COLOR),
+        ("abandoned", merged_runs.num_abandoned, ABANDONED_COLOR),
+        ("unknown", merged_runs.num_unknown, UNKNOWN_COLOR),
+    ]);
+
+    let _ = writeln!(page, "<h2>Realm of death</h2>");
+    let deaths = deaths_by_realm(merged_runs);
+    let last_realm = deaths.keys().copied().max().unwrap_or(0).max(25);
+    let columns : Vec<_> = (1..=last_realm).map(|realm| (realm, deaths.get(&realm).copied().unwrap_or(0))).collect();
+    write_histogram(page, &columns);
+
+    let _ = writeln!(page, "<h2>Top damage sources</h2>");
+    let dmg = merged_runs.damage_to_enemies.clone().unwrap_or_default();
+    let bars : Vec<_> = top_keys(&dmg, report_options.top).into_iter().map(|key| (key.as_str(), dmg[key])).collect();
+    write_bar_chart(page, &bars, DEALT_COLOR);
+
+    let _ = writeln!(page, "<h2>Damage by realm</h2>");
+    let by_realm = damage_by_realm(merged_runs);
+    write_line_chart(page, &[
+        Series { label : "mean damage dealt", color : DEALT_COLOR, points : by_realm.iter().map(|damage| (damage.realm, damage.mean_dealt())).collect() },
+        Series { label : "mean damage taken", color : TAKEN_COLOR, points : by_realm.iter().map(|damage| (damage.realm, damage.mean_taken())).collect() },
+    ]);
+
+    let _ = writeln!(page, "</section>");
+}
+
+fn write_pie_chart(page : &mut String, slices : &[(&str, usize, &str)])
+{
+    let total : usize = slices.iter().map(|slice| slice.1).sum();
+    let (cx, cy, r) = (110.0, 110.0, 100.0);
+
+    let _ = writeln!(page, "<svg width=\"400\" height=\"220\" viewBox=\"0 0 400 220\">");
+
+    if total == 0 {
+        let _ = writeln!(page, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>", cx, cy, r, UNKNOWN_COLOR);
+    }
+
+    let mut angle : f64 = 0.0;
+    for (label, count, color) in slices.iter().filter(|slice| slice.1 > 0) {
+        let fraction = *count as f64 / total as f64;
+
+        // an arc can't start and end at the same point, so a whole pie is just a circle
+        if fraction >= 1.0 {
+            let _ = writeln!(page, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"><title>{}: {}</title></circle>", cx, cy, r, color, label, count);
+            break;
+        }
+
+        let end = angle + fraction * std::f64::consts::TAU;
+        let (x1, y1) = (cx + r * angle.sin(), cy - r * angle.cos());
+        let (x2, y2) = (cx + r * end.sin(), cy - r * end.cos());
+        let large_arc = if fraction > 0.5 { 1 } else { 0 };
+
+        let _ = writeln!(page, "<path d=\"M {} {} L {:.2} {:.2} A {} {} 0 {} 1 {:.2} {:.2} Z\" fill=\"{}\"><title>{}: {}</title></path>",
+            cx, cy, x1, y1, r, r, large_arc, x2, y2, color, label, count);
+
+        angle = end;
+    }
+
+    for (i, (label, count, color)) in slices.iter().enumerate() {
+        let y = 40 + i * 24;
+        let percent = if total == 0 { 0.0 } else { *count as f64 * 100.0 / total as f64 };
+        let _ = writeln!(page, "<rect x=\"240\" y=\"{}\" width=\"14\" height=\"14\" fill=\"{}\"/>", y, color);
+        let _ = writeln!(page, "<text x=\"262\" y=\"{}\">{} {} ({:.1}%)</text>", y + 12, label, count, percent);
+    }
+
+    let _ = writeln!(page, "</svg>");
+}
+
+fn write_histogram(page : &mut String, columns : &[(i64, usize)])
+{
+    let (bar_width, chart_height) = (24, 160);
+    let max = columns.iter().map(|column| column.1).max().unwrap_or(0).max(1);
+    let width = columns.len() * bar_width + 40;
+
+    let _ = writeln!(page, "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", width, chart_height + 40, width, chart_height + 40);
+
+    for (i, (realm, count)) in columns.iter().enumerate() {
+        let height = count * chart_height / max;
+        let x = 20 + i * bar_width;
+
+        let _ = writeln!(page, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>realm {}: {} run(s)</title></rect>",
+            x + 2, 10 + chart_height - height, bar_width - 4, height, DEFEAT_COLOR, realm, count);
+        let _ = writeln!(page, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>", x + bar_width / 2, chart_height + 26, realm);
+
+        if *count > 0 {
+            let _ = writeln!(page, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>", x + bar_width / 2, 6 + chart_height - height, count);
+        }
+    }
+
+    let _ = writeln!(page, "</svg>");
+}
+
+fn write_bar_chart(page : &mut String, bars : &[(&str, usize)], color : &str)
+{
+    let (label_width, bar_space, bar_height) = (180, 400, 22);
+    let max = bars.iter().map(|bar| bar.1).max().unwrap_or(0).max(1);
+    let width = label_width + bar_space + 80;
+    let height = bars.len() * bar_height + 10;
+
+    let _ = writeln!(page, "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", width, height, width, height);
+
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let y = 5 + i * bar_height;
+        let length = value * bar_space / max;
+
+        let _ = writeln!(page, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>", label_width - 8, y + 15, escape(label));
+        let _ = writeln!(page, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>", label_width, y + 2, length, bar_height - 4, color);
+        let _ = writeln!(page, "<text x=\"{}\" y=\"{}\">{}</text>", label_width + length + 6, y + 15, value);
+    }
+
+    let _ = writeln!(page, "</svg>");
+}
+
+struct Series<'a>
+{
+    label : &'a str,
+    color : &'a str,
+    /// (realm, value)
+    points : Vec<(i64, f64)>,
+}
+
+// one polyline per series, sharing the axes
+fn write_line_chart(page : &mut String, series : &[Series])
+{
+    let (left, top, plot_width, plot_height) = (60.0, 10.0, 560.0, 200.0);
+    let points = series.iter().flat_map(|series| series.points.iter());
+    let last_realm = points.clone().map(|point| point.0).max().unwrap_or(1).max(2);
+    let max = points.map(|point| point.1).fold(0.0, f64::max).max(1.0);
+
+    let x = |realm : i64| left + (realm - 1) as f64 * plot_width / (last_realm - 1) as f64;
+    let y = |value : f64| top + plot_height - value * plot_height / max;
+
+    let _ = writeln!(page, "<svg width=\"680\" height=\"270\" viewBox=\"0 0 680 270\">");
+    let _ = writeln!(page, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#888\"/>", left, top + plot_height, left + plot_width, top + plot_height);
+    let _ = writeln!(page, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#888\"/>", left, top, left, top + plot_height);
+    let _ = writeln!(page, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{:.0}</text>", left - 6.0, top + 10.0, max);
+    let _ = writeln!(page, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">0</text>", left - 6.0, top + plot_height);
+
+    for realm in (1..=last_realm).filter(|realm| *realm == 1 || realm % 5 == 0) {
+        let _ = writeln!(page, "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>", x(realm), top + plot_height + 16.0, realm);
+    }
+
+    for (i, Series { label, color, points }) in series.iter().enumerate() {
+        let coordinates : Vec<_> = points.iter().map(|(realm, value)| format!("{:.1},{:.1}", x(*realm), y(*value))).collect();
+        let _ = writeln!(page, "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>", coordinates.join(" "), color);
+
+        let legend_x = left + i as f64 * 200.0;
+        let _ = writeln!(page, "<rect x=\"{}\" y=\"{}\" width=\"14\" height=\"4\" fill=\"{}\"/>", legend_x, top + plot_height + 36.0, color);
+        let _ = writeln!(page, "<text x=\"{}\" y=\"{}\">{} per run, by realm</text>", legend_x + 20.0, top + plot_height + 42.0, label);
+    }
+
+    let _ = writeln!(page, "</svg>");
+}
+
+// the same sections as the text summary, as tables
+fn write_summary(page : &mut String, summary : &Summary)
+{
+    let _ = writeln!(page, "<h2>{}</h2>", escape(&summary.title));
+    let _ = writeln!(page, "<p>runs: {} won, {} lost, {} abandoned, {} unknown / {} total</p>",
+        summary.num_victory, summary.num_defeat, summary.num_abandoned, summary.num_unknown, summary.total_runs);
+
+    for section in summary.sections.iter() {
+        match section {
+            Section::Top(list) => write_top_list(page, list),
+            Section::WinRates { heading, purchases } => {
+                let _ = writeln!(page, "<h3>{}</h3>", escape(heading));
+                let _ = writeln!(page, "<table><tr><th>purchase</th><th class=\"n\">won</th><th class=\"n\">runs</th><th class=\"n\">victories</th><th class=\"n\">defeats</th><th class=\"n\">abandoned</th></tr>");
+                for outcomes in purchases.iter() {
+                    let _ = writeln!(page, "<tr><td>{}</td><td class=\"n\">{:.1}%</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td></tr>",
+                        escape(&outcomes.name), outcomes.win_rate() * 100.0, outcomes.runs, outcomes.num_victory, outcomes.num_defeat, outcomes.num_abandoned);
+                }
+                let _ = writeln!(page, "</table>");
+            },
+        }
+    }
+}
+
+fn write_top_list(page : &mut String, list : &TopList)
+{
+    let _ = writeln!(page, "<h3>{}</h3>", escape(&list.heading));
+
+    let _ = write!(page, "<table><tr><th>name</th><th class=\"n\">total</th>");
+    if list.rates {
+        let _ = write!(page, "<th class=\"n\">per turn</th><th class=\"n\">per run</th>");
+    }
+    let _ = writeln!(page, "</tr>");
+
+    for row in list.rows.iter() {
+        let _ = write!(page, "<tr><td>{}</td><td class=\"n\">{}</td>", escape(&row.name), row.total);
+        if list.rates {
+            let _ = write!(page, "<td class=\"n\">{:.3}</td><td class=\"n\">{:.2}</td>", row.per_turn, row.per_run);
+        }
+        let _ = writeln!(page, "</tr>");
+    }
+
+    let _ = writeln!(page, "</table>");
+
+    if !list.means.is_empty() {
+        let means : Vec<_> = list.means.iter().map(|(label, mean)| format!("{}: {}", label, mean)).collect();
+        let _ = writeln!(page, "<p>{}</p>", means.join("<br>"));
+    }
+}
+
+fn escape(text : &str) -> String
+{
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,124 @@
+//! JSON output, for anything that wants the numbers without scraping the text report.
+//!
+//! Everything is wrapped in the same envelope:
+//!
+//! ```json
+//! { "schema_version": 1, "kind": "merged_runs", "data": { ... } }
+//! ```
+//!
+//! `kind` says what `data` is:
+//!
+//! * `merged_runs` - a [`MergedRuns`]
+//! * `run` - a [`Run`](crate::types::Run)
+//! * `runs` - an array of [`Run`](crate::types::Run)s
+//! * `spell_damage` - a [`SpellDamageReport`](crate::report::SpellDamageReport), with
+//!   `damage_per_cast` `null` for a name never both cast and dealing damage in the same run
+//! * `deaths` - a [`DeathReport`](crate::report::DeathReport), every source of damage in each
+//!   lost run's final realm rather than only the top few the text report shows
+//! * `validation` - `{"stats_files", "runs", "failures", "warnings"}` from the command line tool's
+//!   `validate`, the last two being arrays of error messages
+//!
+//! Fields are named as in the Rust types. Beyond that, version 1 of the schema is:
+//!
+//! * an outcome is one of `"Victory"`, `"Defeat"`, `"Abandoned"` or `"Unknown"`
+//! * a game mode is `{"kind": "Normal"}`, or `{"kind": "Trial" | "Weekly", "name": "..."}`
+//! * a stat map (`spell_casts`, `damage_to_enemies`, `damage_to_wizard`, `items_used`) is an
+//!   object from name to total, or `null` if the section never appeared
+//! * a run's `purchases` is an array of `{"name", "outcome", "realm"}` in the order they were bought,
+//!   a realm's is an array of names
+//! * a realm's `other_sections` maps each title to either a stat map or an array of lines,
+//!   a run's or merged runs' always to a stat map
+//! * `MergedRuns::purchases` is an array of `{"name", "outcome", "realm", "runs"}`, where `runs`
+//!   is how many runs bought `name` in that realm and ended with that outcome
+//! * a run's `id` is the full path of its folder, unique across save folders, and its `name` is the
+//!   folder's name, like `"21"`
+//! * a realm's `modified` and a run's `last_played` are whole seconds since the Unix epoch, or `null`
+//! * `MergedRuns::by_game_mode` is an array of merged runs, each with a `game_mode` field, sorted by mode
+//!
+//! Any change that would break a reader of an older version bumps [`SCHEMA_VERSION`];
+//! new fields alone don't.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Serialize, Serializer};
+
+use crate::types::{GameMode, MergedRuns, Outcome, Purchase};
+
+pub const SCHEMA_VERSION : u32 = 1;
+
+#[derive(Serialize)]
+struct Document<'a, T : Serialize + ?Sized>
+{
+    schema_version : u32,
+    kind : &'a str,
+    data : &'a T,
+}
+
+/// Pretty-printed JSON of `data` in the versioned envelope, see the module docs for the `kind`s.
+pub fn document<T>(kind : &str, data : &T) -> serde_json::Result<String>
+    where T : Serialize + ?Sized
+{
+    serde_json::to_string_pretty(&Document { schema_version : SCHEMA_VERSION, kind, data })
+}
+
+#[derive(Serialize)]
+struct PurchaseCount<'a>
+{
+    name : &'a str,
+    outcome : Outcome,
+    realm : i64,
+    runs : usize,
+}
+
+// a purchase can't be a JSON object key, so the counts become an array instead
+pub(crate) fn serialize_purchase_counts<S>(purchases : &Option<HashMap<Purchase, usize>>, serializer : S) -> Result<S::Ok, S::Error>
+    where S : Serializer
+{
+    let purchases = match purchases {
+        Some(purchases) => purchases,
+        None => return serializer.serialize_none(),
+    };
+
+    let mut counts : Vec<_> = purchases.iter()
+        .map(|(purchase, runs)| PurchaseCount { name : &purchase.name, outcome : purchase.outcome, realm : purchase.realm, runs : *runs })
+        .collect();
+    counts.sort_by(|a, b| { (a.name, a.realm, a.outcome).cmp(&(b.name, b.realm, b.outcome)) });
+
+    serializer.collect_seq(counts)
+}
+
+#[derive(Serialize)]
+struct ModeRuns<'a>
+{
+    game_mode : &'a GameMode,
+    #[serde(flatten)]
+    runs : &'a MergedRuns,
+}
+
+// same for a game mode with a name
+pub(crate) fn serialize_by_game_mode<S>(by_game_mode : &Option<HashMap<GameMode, MergedRuns>>, serializer : S) -> Result<S::Ok, S::Error>
+    where S : Serializer
+{
+    let by_game_mode = match by_game_mode {
+        Some(by_game_mode) => by_game_mode,
+        None => return serializer.serialize_none(),
+    };
+
+    let mut modes : Vec<_> = by_game_mode.iter()
+        .map(|(game_mode, runs)| ModeRuns { game_mode, runs })
+        .collect();
+    modes.sort_by(|a, b| { a.game_mode.cmp(b.game_mode) });
+
+    serializer.collect_seq(modes)
+}
+
+// seconds are plenty, and simpler to read than serde's own secs and nanos
+pub(crate) fn serialize_time<S>(time : &Option<SystemTime>, serializer : S) -> Result<S::Ok, S::Error>
+    where S : Serializer
+{
+    match time.and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+        Some(since_epoch) => serializer.serialize_some(&since_epoch.as_secs()),
+        None => serializer.serialize_none(),
+    }
+}
@@ -0,0 +1,25 @@
+//! Parser and aggregator for Rift Wizard's per-realm `stats.level_N.txt` files.
+//!
+//! A save folder holds one folder per run, and each run folder holds one stats file per realm.
+//! [`gather_stats_from_file`] turns a stats file into a [`Realm`], [`generate_run_report`]
+//! folds the realms of a run into a [`Run`], and [`MergedRuns::merge_run`] sums runs together.
+//! [`read_save_folders`] does all of that for a list of save folders, and [`find_save_folders`]
+//! looks for the game's save folders in the Steam libraries on this machine.
+
+pub mod export;
+pub mod filter;
+pub mod hashmap;
+pub mod json;
+pub mod load;
+pub mod parse;
+pub mod query;
+pub mod report;
+pub mod saves;
+pub mod types;
+
+pub use crate::filter::RunFilter;
+pub use crate::load::{find_run_folders, generate_run_report, read_all_saves, read_save, read_save_folders, run_id, run_name, ReadOptions, ReadProblems, ReadSaveError};
+pub use crate::parse::{gather_stats_from_file, gather_stats_from_file_lenient, GatherError, GatherErrorKind, Location};
+pub use crate::query::{Query, QueryError};
+pub use crate::saves::find_save_folders;
+pub use crate::types::{GameMode, MergedRuns, Outcome, Purchase, Realm, Run, RunSort, Section};
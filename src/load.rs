@@ -0,0 +1,341 @@
+use std::fs;
+use std::io;
+use std::fmt;
+use std::error;
+use std::path::*;
+use std::cmp;
+use std::collections::HashSet;
+
+use crate::filter::RunFilter;
+use crate::parse::*;
+use crate::types::{Run, Realm, MergedRuns, Outcome, Purchase};
+use crate::hashmap::{HashMapExtensions, merge_add_nested};
+
+/// Every variant carries the path of the folder or file that failed.
+#[derive(Debug)]
+pub enum ReadSaveError
+{
+    Io(PathBuf, io::Error),
+    Gather(PathBuf, GatherError),
+    IllegalFilename(PathBuf),
+    InvalidDirectory(PathBuf)
+}
+
+impl fmt::Display for ReadSaveError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            ReadSaveError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            ReadSaveError::Gather(path, err) => match err.location {
+                // the location already names the file
+                Some(_) => write!(f, "{}", err),
+                None => write!(f, "{}: {}", path.display(), err),
+            },
+            ReadSaveError::IllegalFilename(path) => write!(f, "{}: filename is not valid unicode", path.display()),
+            ReadSaveError::InvalidDirectory(path) => write!(f, "{}: not a directory", path.display()),
+        }
+    }
+}
+
+impl error::Error for ReadSaveError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)>
+    {
+        match self {
+            ReadSaveError::Io(_, err) => Some(err),
+            ReadSaveError::Gather(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// How the `read_*` functions react to a folder or stats file that fails to read, and which runs they keep.
+#[derive(Default, Debug, Clone)]
+pub struct ReadOptions
+{
+    /// Record the failure in `ReadProblems::failures` and carry on with the next file or folder,
+    /// instead of returning the first error.
+    pub keep_going : bool,
+    /// Skip malformed lines inside a stats file's sections, recording them in `ReadProblems::warnings`,
+    /// instead of failing the whole file.
+    pub lenient : bool,
+    /// Runs that don't match are left out of the `MergedRuns` from `read_all_saves` and `read_save_folders`.
+    pub filter : RunFilter,
+}
+
+/// Everything that went wrong while reading but didn't stop us.
+#[derive(Default, Debug)]
+pub struct ReadProblems
+{
+    /// files and folders that failed while keeping going, each leaving out the run it was in,
+    /// or the whole save folder
+    pub failures : Vec<ReadSaveError>,
+    /// lines that were skipped because of `ReadOptions::lenient`
+    pub warnings : Vec<ReadSaveError>,
+}
+
+/// Returns `err` if we should stop, or records it as a failure if we are keeping going.
+fn fail(err : ReadSaveError, options : &ReadOptions, problems : &mut ReadProblems) -> Result<(), ReadSaveError>
+{
+    if options.keep_going {
+        problems.failures.push(err);
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+/// Reads every save folder in order and merges all of their runs together.
+pub fn read_save_folders<P>(save_folders : &[P], options : &ReadOptions, problems : &mut ReadProblems) -> Result<MergedRuns, ReadSaveError>
+    where P : AsRef<Path>
+{
+    let mut merged_runs = MergedRuns { ..Default::default() };
+
+    for save_folder in save_folders {
+        let save_folder = save_folder.as_ref().to_path_buf().into_boxed_path();
+
+        // checked here rather than left to read_all_saves so keeping going doesn't lose the runs merged so far
+        if !save_folder.is_dir() {
+            fail(ReadSaveError::InvalidDirectory(save_folder.to_path_buf()), options, problems)?;
+            continue;
+        }
+
+        merged_runs = read_all_saves(save_folder, Some(merged_runs), options, problems)?;
+    }
+
+    Ok(merged_runs)
+}
+
+/// The identifier of the run in `run_folder`: the folder's full path, so runs with the same name in
+/// different save folders stay apart, and the same run gets the same id however its save folder was given.
+pub fn run_id(run_folder : &Path) -> String
+{
+    fs::canonicalize(run_folder).unwrap_or_else(|_| run_folder.to_path_buf()).to_string_lossy().into_owned()
+}
+
+/// The name of the run in `run_folder`, which is just the folder's name (e.g. `21`).
+pub fn run_name(run_folder : &Path) -> String
+{
+    run_folder.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Every run folder called `run` (e.g. `21`) in the save folders, in order. If no save folder has one,
+/// `run` is taken as a path to the run folder itself. More than one means the name alone is ambiguous.
+pub fn find_run_folders<P>(save_folders : &[P], run : &str) -> Vec<PathBuf>
+    where P : AsRef<Path>
+{
+    let mut found : Vec<_> = save_folders.iter()
+        .map(|save_folder| save_folder.as_ref().join(run))
+        .filter(|path| path.is_dir())
+        .collect();
+
+    // the same save folder given twice is still only one run
+    let mut seen = HashSet::new();
+    found.retain(|path| seen.insert(run_id(path)));
+
+    if found.is_empty() && Path::new(run).is_dir() {
+        found.push(PathBuf::from(run));
+    }
+
+    found
+}
+
+/// Reads every run folder inside a Rift Wizard `saves` folder, merging each run into `merged_runs`
+/// (or into a fresh `MergedRuns` if none is given).
+pub fn read_all_saves(save_folder : Box<Path>, merged_runs : Option<MergedRuns>, options : &ReadOptions, problems : &mut ReadProblems) -> Result<MergedRuns, ReadSaveError>
+{
+    if !save_folder.is_dir() {
+        return Err(ReadSaveError::InvalidDirectory(save_folder.to_path_buf()));
+    }
+
+    let mut merged_runs = merged_runs.unwrap_or_default();
+
+    for entry in fs::read_dir(&save_folder).map_err(|err| ReadSaveError::Io(save_folder.to_path_buf(), err))? {
+        let path = entry.map_err(|err| ReadSaveError::Io(save_folder.to_path_buf(), err))?.path();
+
+        if path.is_dir() {
+            let id = run_id(&path);
+            let name = run_name(&path);
+            
+            match read_save(path.into_boxed_path(), options, problems) {
+                Err(err) => fail(err, options, problems)?,
+                Ok(r) => {
+                    let mut run = generate_run_report(r);
+                    run.id = id;
+                    run.name = name;
+
+                    if options.filter.matches(&run) {
+                        merged_runs = merged_runs.merge_run(run);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(merged_runs)
+}
+
+/// Reads every `stats*.txt` file in a single run folder, one `Realm` per file, in directory order.
+///
+/// Any stats file that fails fails the whole run, even when keeping going: a run missing a realm
+/// would end up with the wrong outcome. `read_all_saves` records that failure and leaves the run out.
+pub fn read_save(save_folder : Box<Path>, options : &ReadOptions, problems : &mut ReadProblems) -> Result<Vec<Realm>, ReadSaveError>
+{
+    if !save_folder.is_dir() {
+        return Err(ReadSaveError::InvalidDirectory(save_folder.to_path_buf()));
+    }
+
+    let mut realms = Vec::with_capacity(1);
+
+    for entry in fs::read_dir(&save_folder).map_err(|err| ReadSaveError::Io(save_folder.to_path_buf(), err))? {
+        let path = entry.map_err(|err| ReadSaveError::Io(save_folder.to_path_buf(), err))?.path();
+
+        let filename = match path.file_name() {
+            Some(filename) if path.is_file() => filename,
+            _ => continue,
+        };
+
+        let filename = match filename.to_str() {
+            Some(f) => f,
+            None => return Err(ReadSaveError::IllegalFilename(path.clone())),
+        };
+
+        // anything else in a run folder is the game's own save data, which we don't read
+        if !(filename.starts_with("stats") && filename.ends_with(".txt")) {
+            continue;
+        }
+
+        let result = if options.lenient {
+            gather_stats_from_file_lenient(path.clone().into_boxed_path()).map(|(realm, warnings)| {
+                for warning in warnings {
+                    problems.warnings.push(ReadSaveError::Gather(path.clone(), warning));
+                }
+                realm
+            })
+        } else {
+            gather_stats_from_file(path.clone().into_boxed_path())
+        };
+
+        match result {
+            Ok(mut realm) => {
+                realm.modified = path.metadata().and_then(|metadata| metadata.modified()).ok();
+                realms.push(realm);
+            },
+            Err(err) => return Err(ReadSaveError::Gather(path, err)),
+        }
+    }
+
+    Ok(realms)
+}
+
+/// Folds the realms of one run into a `Run`, sorting them by realm number first.
+/// The run's `id` and `name` are left empty as the realms don't know which folder they came from.
+///
+/// A run counts as a victory only if every realm was a victory and all 25 realms are present;
+/// a run that stopped early without a defeat is `Abandoned`.
+pub fn generate_run_report(mut realms : Vec<Realm>) -> Run
+{
+    realms.sort_by(|a, b| { a.realm_number.cmp(&b.realm_number) });
+
+    let mut run = Run {
+        realms : realms.len() as i64,
+        outcome : Outcome::Victory,
+        // every realm of a run has the same header, so the first one will do
+        game_mode : realms.first().map(|realm| realm.game_mode.clone()).unwrap_or_default(),
+        ..Default::default() };
+
+
+    for realm in realms.iter() {
+        if run.outcome == Outcome::Victory {
+            match realm.outcome {
+                Outcome::Unknown => run.outcome = Outcome::Unknown,
+                Outcome::Defeat => run.outcome = Outcome::Defeat,
+                _ => (),
+            }
+        }
+        run.turns_taken_run = cmp::max(run.turns_taken_run, realm.turns_taken_run);
+        run.spell_casts = run.spell_casts.merge_add(realm.spell_casts.clone());
+        run.damage_to_enemies = run.damage_to_enemies.merge_add(realm.damage_to_enemies.clone());
+        run.damage_to_wizard = run.damage_to_wizard.merge_add(realm.damage_to_wizard.clone());
+        run.items_used = run.items_used.merge_add(realm.items_used.clone());
+        
+        if let Some(purchases) = &realm.purchases {
+            // a realm's purchases come to us unordered, so sort them to keep runs comparable
+            let mut names : Vec<_> = purchases.iter().cloned().collect();
+            names.sort();
+            
+            let run_purchases = run.purchases.get_or_insert_with(Vec::new);
+            for name in names {
+                run_purchases.push(Purchase { name, outcome : Outcome::Unknown, realm : realm.realm_number });
+            }
+        }
+        run.other_sections = merge_add_nested(run.other_sections, realm.other_sections.as_ref().map(|sections| {
+            sections.iter().map(|(title, section)| (title.clone(), section.to_counts())).collect()
+        }));
+    }
+    
+    run.last_played = realms.iter().filter_map(|realm| realm.modified).max();
+    run.realm_stats = Some(realms);
+
+    if run.outcome == Outcome::Victory && run.realms < 25
+    {
+        run.outcome = Outcome::Abandoned;
+    }
+    
+    // only now do we know how the run ended
+    for purchase in run.purchases.iter_mut().flatten() {
+        purchase.outcome = run.outcome;
+    }
+
+    run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn realm(realm_number : i64, outcome : Outcome, purchases : &[&str]) -> Realm
+    {
+        Realm {
+            realm_number,
+            outcome,
+            purchases : Some(purchases.iter().map(|name| name.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    fn purchase_outcomes(run : &Run) -> Vec<(&str, Outcome, i64)>
+    {
+        run.purchases.iter().flatten().map(|purchase| (purchase.name.as_str(), purchase.outcome, purchase.realm)).collect()
+    }
+
+    #[test]
+    fn purchases_take_the_outcome_of_the_whole_run()
+    {
+        let run = generate_run_report(vec![
+            realm(2, Outcome::Defeat, &["Blink"]),
+            realm(1, Outcome::Victory, &["Fireball", "Chain Lightning"]),
+        ]);
+
+        assert_eq!(run.outcome, Outcome::Defeat);
+        assert_eq!(purchase_outcomes(&run), vec![
+            ("Chain Lightning", Outcome::Defeat, 1),
+            ("Fireball", Outcome::Defeat, 1),
+            ("Blink", Outcome::Defeat, 2),
+        ]);
+    }
+
+    #[test]
+    fn a_run_short_of_25_realms_without_a_defeat_is_abandoned()
+    {
+        let run = generate_run_report((1..=24).map(|number| realm(number, Outcome::Victory, &["Fireball"])).collect());
+
+        assert_eq!(run.outcome, Outcome::Abandoned);
+        assert!(purchase_outcomes(&run).iter().all(|(_, outcome, _)| *outcome == Outcome::Abandoned));
+
+        let run = generate_run_report((1..=25).map(|number| realm(number, Outcome::Victory, &["Fireball"])).collect());
+
+        assert_eq!(run.outcome, Outcome::Victory);
+        assert_eq!(purchase_outcomes(&run).len(), 25);
+        assert!(purchase_outcomes(&run).iter().all(|(_, outcome, _)| *outcome == Outcome::Victory));
+    }
+}
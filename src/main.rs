@@ -1,237 +1,696 @@
+mod html;
+mod markdown;
+mod summary;
+mod text;
+mod tui;
 
-mod parse;
-mod types;
-mod hashmap;
+use riftwizardstats::{GatherError, GatherErrorKind, MergedRuns, ReadOptions, ReadProblems, ReadSaveError};
+use riftwizardstats::{Outcome, Query, QueryError, RunFilter};
+use riftwizardstats::filter::parse_date;
+use riftwizardstats::{find_run_folders, find_save_folders, generate_run_report, read_save, read_save_folders, run_id, run_name, Run, RunSort};
+use riftwizardstats::{export, json};
+use riftwizardstats::report::{self, PurchaseSort};
 
-use crate::parse::*;
-use crate::types::{Run,Realm, MergedRuns, Outcome};
-use crate::hashmap::*;
-use crate::hashmap::HashMapExtensions;
+use crate::text::ReportOptions;
 
 use clap::{crate_authors, crate_version};
+use serde::Serialize;
 
-use std::fs;
-use std::path::*;
-use std::io;
-use std::io::BufRead;
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::cmp;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime};
 
-use clap::{Arg, App};
+use clap::{Arg, ArgGroup, App, AppSettings, ArgMatches, SubCommand};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format
+{
+    Text,
+    Json,
+    Html,
+    Markdown,
+}
 
 fn main() {
     let matches = App::new("Rift Wizard Stats")
                           .version(crate_version!())
                           .author(crate_authors!())
-                          .about("Turns Rift Wizard logs into stats")
+                          .about("Turns Rift Wizard logs into stats. Shows the summary if no command is given")
+                          .setting(AppSettings::VersionlessSubcommands)
+                          .arg(
+                                Arg::with_name("saves")
+                                    .long("saves")
+                                    .short("s")
+                                    .value_name("SAVELOCATION")
+                                    .help("Location of Rift Wizard's saves folder, can be given more than once. Found in your Steam libraries if not given")
+                                    .multiple(true)
+                                    .number_of_values(1)
+                                    .global(true),
+                            )
+                          .arg(
+                                Arg::with_name("print-save-path")
+                                    .long("print-save-path")
+                                    .help("Print the save folders that would be read, then exit")
+                                    .global(true),
+                            )
+                          .arg(
+                                Arg::with_name("format")
+                                    .long("format")
+                                    .short("f")
+                                    .help("Output format")
+                                    .possible_values(&["text", "json", "html", "markdown"])
+                                    .default_value("text")
+                                    .global(true),
+                            )
+                          .arg(
+                                Arg::with_name("keep-going")
+                                    .long("keep-going")
+                                    .short("k")
+                                    .help("Skip files that fail to read, finish the report, then list every failure")
+                                    .global(true),
+                            )
+                          .arg(
+                                Arg::with_name("lenient")
+                                    .long("lenient")
+                                    .help("Skip malformed lines inside a stats file instead of failing the file, then list them as warnings")
+                                    .global(true),
+                            )
                           .arg(
                                 Arg::with_name("SAVELOCATION")
-                                    .help("Location of Rift Wizard's saves folder")
-                                    // TODO - check registry for folder and so on?
-                                    .default_value(r#"C:\Program Files (x86)\Steam\steamapps\common\Rift Wizard\RiftWizard\saves\"#)
-                                    .index(1)
+                                    .help("Location of Rift Wizard's saves folder, the same as giving it with --saves")
                                     .multiple(true),
                             )
+                          .args(&filter_args())
+                          .subcommand(summary_command())
+                          .subcommand(
+                                SubCommand::with_name("run")
+                                    .about("Shows one run in detail, realm by realm")
+                                    .arg(run_arg())
+                                    .arg(
+                                        Arg::with_name("timeline")
+                                            .long("timeline")
+                                            .help("Only show what the run bought, realm by realm"),
+                                    )
+                            )
+                          .subcommand(
+                                SubCommand::with_name("list")
+                                    .about("Lists every run with its outcome, realms and turns")
+                                    .arg(
+                                        Arg::with_name("sort")
+                                            .long("sort")
+                                            .help("How to sort the runs")
+                                            .possible_values(&["id", "outcome", "realms", "turns"])
+                                            .default_value("id"),
+                                    )
+                            )
+                          .subcommand(
+                                SubCommand::with_name("compare")
+                                    .about("Shows several runs side by side")
+                                    .arg(run_arg().multiple(true).min_values(2))
+                            )
+                          .subcommand(
+                                SubCommand::with_name("spells")
+                                    .about("Damage per cast, casts per turn and share of damage for each spell, over every run or in one")
+                                    .arg(run_arg().required(false))
+                            )
+                          .subcommand(
+                                SubCommand::with_name("deaths")
+                                    .about("What did the damage in the final realm of each lost run, and the deadliest enemies over all of them")
+                                    .arg(
+                                        Arg::with_name("top")
+                                            .long("top")
+                                            .value_name("N")
+                                            .help("How many damage sources to show for each death, and how many of the deadliest enemies")
+                                            .default_value("5"),
+                                    )
+                            )
+                          .subcommand(
+                                SubCommand::with_name("export")
+                                    .about("Writes every run out as tables for other tools")
+                                    .arg(
+                                        Arg::with_name("csv")
+                                            .long("csv")
+                                            .value_name("DIR")
+                                            .help("Write runs.csv and stats.csv into DIR, creating it if needed"),
+                                    )
+                                    .arg(
+                                        Arg::with_name("sqlite")
+                                            .long("sqlite")
+                                            .value_name("FILE")
+                                            .help("Write runs, realms, stat_entries and purchases tables into the SQLite database FILE, replacing runs already in it"),
+                                    )
+                                    .group(
+                                        ArgGroup::with_name("destination")
+                                            .args(&["csv", "sqlite"])
+                                            .multiple(true)
+                                            .required(true),
+                                    )
+                            )
+                          .subcommand(
+                                SubCommand::with_name("tui")
+                                    .about("Browse every run in an interactive terminal view")
+                            )
+                          .subcommand(
+                                SubCommand::with_name("validate")
+                                    .about("Reads every stats file and lists any that can't be read, without a report")
+                            )
                           .get_matches();
-    
-    let save_location_list : Vec<_> = matches.values_of("SAVELOCATION").unwrap().collect();
-    
-    let all_runs =
-    {
-        let mut all_runs = None;
+
+    // no command means the summary, with all of its defaults
+    let (command, command_matches) = match matches.subcommand() {
+        (name, Some(sub_matches)) => (name, sub_matches.clone()),
+        _ => ("summary", summary_command().get_matches_from(vec!["summary"])),
+    };
+
+    // global options are copied down into the command's matches, but only when there is a command
+    let global_matches = if matches.subcommand_name().is_some() { &command_matches } else { &matches };
+
+    // save folders without --saves are how it was run before there were commands, so that still works
+    let mut save_location_list : Vec<PathBuf> = global_matches.values_of_os("saves").into_iter().flatten().map(PathBuf::from).collect();
+    save_location_list.extend(matches.values_of_os("SAVELOCATION").into_iter().flatten().map(PathBuf::from));
+
+    if save_location_list.is_empty() {
+        save_location_list = find_save_folders();
+    }
+
+    if global_matches.is_present("print-save-path") {
         for save_location in save_location_list {
-            all_runs = {
-                let rift_wiz_save_folder = PathBuf::from(save_location);
-                
-                let result = read_all_saves(rift_wiz_save_folder.into_boxed_path(), all_runs);
-                
-                match result {
-                    // TODO - add proper error handling
-                    Err(err) => {println!("\nerror: {:?}", err); panic!("TODO add proper error handling"); },
-                    Ok(r) => Some(r)
+            println!("{}", save_location.display());
+        }
+        return;
+    }
+
+    let format = match global_matches.value_of("format").unwrap() {
+        "text" => Format::Text,
+        "json" => Format::Json,
+        "html" => Format::Html,
+        "markdown" => Format::Markdown,
+        other => unreachable!("clap let through an unknown format {}", other),
+    };
+
+    // html and markdown are reports of the summary, a page of one run or a run list isn't worth having yet
+    let format_works = match format {
+        Format::Text => true,
+        Format::Json => command != "tui",
+        Format::Html | Format::Markdown => command == "summary",
+    };
+
+    if !format_works {
+        eprintln!("error: --format {} doesn't work with {}", global_matches.value_of("format").unwrap(), command);
+        process::exit(EXIT_USAGE);
+    }
+
+    let options = ReadOptions {
+        keep_going : global_matches.is_present("keep-going") || command == "validate",
+        lenient : global_matches.is_present("lenient"),
+        filter : run_filter(global_matches),
+    };
+
+    let mut problems = ReadProblems { ..Default::default() };
+
+    match command {
+        "run" => {
+            let run = read_single_run(&save_location_list, command_matches.value_of("RUN").unwrap(), &options, &mut problems);
+
+            match format {
+                Format::Text if command_matches.is_present("timeline") => text::print_purchase_timeline(&run),
+                Format::Text => text::print_run_detail(&run),
+                Format::Json => print_json("run", &run),
+                Format::Html | Format::Markdown => unreachable!("only the summary has a report format"),
+            }
+        },
+        "compare" => {
+            let runs : Vec<_> = command_matches.values_of("RUN").unwrap()
+                .map(|run_name| read_single_run(&save_location_list, run_name, &options, &mut problems))
+                .collect();
+
+            match format {
+                Format::Text => text::print_run_comparison(&runs),
+                Format::Json => print_json("runs", &runs),
+                Format::Html | Format::Markdown => unreachable!("only the summary has a report format"),
+            }
+        },
+        "list" => {
+            let all_runs = read_all_runs(&save_location_list, &options, &mut problems);
+
+            let sort = match command_matches.value_of("sort").unwrap() {
+                "outcome" => RunSort::Outcome,
+                "realms" => RunSort::Realms,
+                "turns" => RunSort::Turns,
+                _ => RunSort::Id,
+            };
+
+            match format {
+                Format::Text => text::print_run_list(&all_runs, sort),
+                Format::Json => print_json("runs", &all_runs.sorted_runs(sort)),
+                Format::Html | Format::Markdown => unreachable!("only the summary has a report format"),
+            }
+        },
+        "spells" => {
+            let report = match command_matches.value_of("RUN") {
+                Some(run_name) => report::spell_damage(&[read_single_run(&save_location_list, run_name, &options, &mut problems)]),
+                None => report::spell_damage(read_all_runs(&save_location_list, &options, &mut problems).runs.iter().flatten()),
+            };
+
+            match format {
+                Format::Text => text::print_spell_damage(&report),
+                Format::Json => print_json("spell_damage", &report),
+                Format::Html | Format::Markdown => unreachable!("only the summary has a report format"),
+            }
+        },
+        "deaths" => {
+            let all_runs = read_all_runs(&save_location_list, &options, &mut problems);
+            let report = report::deaths(all_runs.sorted_runs(RunSort::Id));
+
+            match format {
+                Format::Text => text::print_deaths(&report, parse_count_arg(&command_matches, "top")),
+                Format::Json => print_json("deaths", &report),
+                Format::Html | Format::Markdown => unreachable!("only the summary has a report format"),
+            }
+        },
+        "export" => {
+            let all_runs = read_all_runs(&save_location_list, &options, &mut problems);
+            let runs = all_runs.sorted_runs(RunSort::Id);
+
+            // the tables are keyed by run id, so the same run twice would overwrite itself
+            let duplicates = export::duplicate_ids(runs.iter().copied());
+            if !duplicates.is_empty() {
+                eprintln!("error: these runs were read more than once, is a save folder given twice?");
+                for id in duplicates {
+                    eprintln!("  {}", id);
                 }
+                process::exit(EXIT_USAGE);
+            }
+
+            if let Some(csv_dir) = command_matches.value_of_os("csv") {
+                export_csv(&runs, Path::new(csv_dir));
+            }
+            if let Some(database) = command_matches.value_of_os("sqlite") {
+                export_sqlite(&runs, Path::new(database));
+            }
+        },
+        "tui" => {
+            let all_runs = read_all_runs(&save_location_list, &options, &mut problems);
+
+            if let Err(err) = tui::run(&all_runs) {
+                eprintln!("error: {}", err);
+                process::exit(EXIT_IO);
+            }
+        },
+        "validate" => {
+            let all_runs = read_all_runs(&save_location_list, &options, &mut problems);
+
+            match format {
+                Format::Text => text::print_validation(&all_runs, &problems),
+                Format::Json => print_json("validation", &Validation::new(&all_runs, &problems)),
+                Format::Html | Format::Markdown => unreachable!("only the summary has a report format"),
+            }
+        },
+        _ => {
+            let all_runs = read_all_runs(&save_location_list, &options, &mut problems);
+
+            let report_options = ReportOptions {
+                purchase_sort : match command_matches.value_of("purchase-sort").unwrap() {
+                    "runs" => PurchaseSort::Runs,
+                    "name" => PurchaseSort::Name,
+                    _ => PurchaseSort::WinRate,
+                },
+                min_runs : parse_count_arg(&command_matches, "min-runs"),
+                top : parse_count_arg(&command_matches, "top"),
             };
+
+            match format {
+                Format::Text => text::print_merged_runs_info(all_runs, &report_options),
+                Format::Json => print_json("merged_runs", &all_runs),
+                Format::Html => html::print_merged_runs_html(all_runs, &report_options),
+                Format::Markdown => markdown::print_merged_runs_markdown(all_runs, &report_options),
+            }
+        },
+    }
+
+    print_problems_and_exit(&problems);
+}
+
+#[derive(Serialize)]
+struct Validation
+{
+    stats_files : usize,
+    runs : usize,
+    failures : Vec<String>,
+    warnings : Vec<String>,
+}
+
+impl Validation {
+    fn new(merged_runs : &MergedRuns, problems : &ReadProblems) -> Validation
+    {
+        let runs = merged_runs.runs.as_deref().unwrap_or_default();
+
+        Validation {
+            stats_files : runs.iter().map(|run| run.realm_stats.iter().flatten().count()).sum(),
+            runs : runs.len(),
+            failures : problems.failures.iter().map(|err| err.to_string()).collect(),
+            warnings : problems.warnings.iter().map(|err| err.to_string()).collect(),
         }
-        
-        all_runs.unwrap()
-    };
-    
-    print_merged_runs_info(all_runs);
+    }
 }
 
-#[derive(Debug)]
-enum ReadSaveError
+fn write_csv_files(runs : &[&Run], dir : &Path) -> csv::Result<()>
 {
-    Io(io::Error),
-    Gather(GatherError),
-    IllegalFilename,
-    InvalidDirectory(String)
+    fs::create_dir_all(dir)?;
+    export::write_runs_csv(runs.iter().copied(), File::create(dir.join("runs.csv"))?)?;
+    export::write_stats_csv(runs.iter().copied(), File::create(dir.join("stats.csv"))?)
 }
 
-fn read_all_saves(save_folder : Box<Path>, merged_runs : Option<MergedRuns>) -> Result<MergedRuns, ReadSaveError>
+// exits if a file can't be written
+fn export_csv(runs : &[&Run], dir : &Path)
 {
-    if !save_folder.is_dir() {
-        return Err(ReadSaveError::InvalidDirectory(
-            save_folder.to_str().unwrap_or("failed to stringify Save Folder").to_string()
-        ));
+    match write_csv_files(runs, dir) {
+        Ok(()) => println!("wrote {} run(s) to {}", runs.len(), dir.display()),
+        Err(err) => {
+            eprintln!("error: couldn't export to {}: {}", dir.display(), err);
+            process::exit(EXIT_IO);
+        }
     }
-    
-    let mut merged_runs = match merged_runs {
-        Some(r) => r,
-        None => MergedRuns { ..Default::default() },
-    };
-    
-    for entry in fs::read_dir(save_folder).map_err(ReadSaveError::Io)? {
-        let path = entry.map_err(ReadSaveError::Io)?.path();
-        
-        if path.is_dir() {
-            let result = read_save(path.into_boxed_path());
-            
-            match result {
-                Err(err) => println!("\nrealm read error: {:?}", err),
-                Ok(r) => { merged_runs = merged_runs.merge_run(generate_run_report(r)); } 
-            }
+}
+
+// exits if the database can't be written
+fn export_sqlite(runs : &[&Run], database : &Path)
+{
+    let written = rusqlite::Connection::open(database)
+        .and_then(|mut connection| export::write_sqlite(runs.iter().copied(), &mut connection));
+
+    match written {
+        Ok(written) => println!("wrote {} run(s) to {}", written, database.display()),
+        Err(err) => {
+            eprintln!("error: couldn't export to {}: {}", database.display(), err);
+            process::exit(EXIT_IO);
         }
     }
-    
-    Ok(merged_runs)
-}
-
-fn read_save(save_folder : Box<Path>) -> Result<Vec<Realm>, ReadSaveError>
-{
-    if !save_folder.is_dir() {
-        return Err(ReadSaveError::InvalidDirectory(
-            save_folder.to_str().unwrap_or("failed to stringify Save Folder").to_string()
-        ));
-    }
-    
-    let mut realms = Vec::with_capacity(1);
-    
-    for entry in fs::read_dir(save_folder).map_err(ReadSaveError::Io)? {
-        let path = entry.map_err(ReadSaveError::Io)?.path();
-        
-        if path.is_file() {
-            match path.file_name() {
-                Some(filename) => {
-                    if filename.to_str().ok_or(ReadSaveError::IllegalFilename)?.starts_with("stats")
-                        && filename.to_str().ok_or(ReadSaveError::IllegalFilename)?.ends_with(".txt")
-                    {
-                        let result = gather_stats_from_file(path.into_boxed_path()).map_err(ReadSaveError::Gather)?;
-                        
-                        println!("{:?}", result);
-                        
-                        realms.push(result);
-                        
-                        /*match result {
-                            Err(err) => println!("{:?}", err),
-                            Ok(realm) => println!("{:?}", realm?)
-                        }*/
-                    } else {
-                        println!("skipping {}", filename.to_str().unwrap());
-                    }
-                },
-                None => println!("{} is not a file?", path.display())
-            }
-        
-        //let filename = rift_wiz_save_folder.join("21/stats.level_25.txt").into_boxed_path();
-            
+}
+
+fn print_json<T>(kind : &str, data : &T)
+    where T : Serialize + ?Sized
+{
+    match json::document(kind, data) {
+        Ok(document) => println!("{}", document),
+        Err(err) => {
+            eprintln!("error: couldn't write the json: {}", err);
+            process::exit(EXIT_IO);
         }
     }
-    
-    Ok(realms)
 }
 
-fn print_merged_runs_info(merged_runs : MergedRuns)
+fn summary_command() -> App<'static, 'static>
 {
-    println!("");
-    println!("====================");
-    println!("MERGED RUNS");
-    println!("{:?}", merged_runs);
-    
-    let total_runs = merged_runs.num_victory + merged_runs.num_defeat + merged_runs.num_abandoned + merged_runs.num_unknown;
-    println!("runs: {} won, {} lost, {} abandoned, {} unknown / {} total", merged_runs.num_victory, merged_runs.num_defeat, merged_runs.num_abandoned, merged_runs.num_unknown, total_runs);
-    
-    {
-        let dmg = lazy_init(merged_runs.damage_to_enemies);
-    
-        println!("====================================");
-        println!("DAMAGE TO ENEMIES (TOP 10 SUM OF ALL RUNS)");
-        print_top_ten(&dmg);
-        
-        println!("");
-        println!("mean damage per turn: {}", get_mean_per_turn(&dmg, merged_runs.turns_taken));
-        println!("");
-    }
-    
-    {
-        let dmg = lazy_init(merged_runs.damage_to_wizard);
-        println!("===================================");
-        println!("DAMAGE TO WIZARD (TOP 10 SUM OF ALL RUNS)");
-        print_top_ten(&dmg);
-        
-        println!("");
-        println!("mean damage per turn: {}", get_mean_per_turn(&dmg, merged_runs.turns_taken));
-        println!("");
-    }
-}
-
-fn generate_run_report(mut realms : Vec<Realm>) -> Run
-{
-    realms.sort_by(|a, b| { a.realm_number.cmp(&b.realm_number) });
-    
-    let mut run = Run {
-        realms : realms.len() as i64,
-        outcome : Outcome::Victory,
-        ..Default::default() };
-    
-    
-    for realm in realms {
-        if run.outcome == Outcome::Victory {
-            match realm.outcome {
-                Outcome::Unknown => run.outcome = Outcome::Unknown,
-                Outcome::Defeat => run.outcome = Outcome::Defeat,
-                _ => (),
+    SubCommand::with_name("summary")
+        .about("Totals, top lists and win rates over every run")
+        .arg(
+            Arg::with_name("purchase-sort")
+                .long("purchase-sort")
+                .help("How to sort the win rate by purchase section")
+                .possible_values(&["win-rate", "runs", "name"])
+                .default_value("win-rate"),
+        )
+        .arg(
+            Arg::with_name("min-runs")
+                .long("min-runs")
+                .value_name("N")
+                .help("Leave purchases bought in fewer runs than this out of the win rate by purchase section")
+                .default_value("3"),
+        )
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .value_name("N")
+                .help("How many entries to show in each top list")
+                .default_value("10"),
+        )
+}
+
+// these pick which runs go into every report of all runs
+fn filter_args() -> Vec<Arg<'static, 'static>>
+{
+    vec![
+        Arg::with_name("outcome")
+            .long("outcome")
+            .help("Only runs with this outcome, can be given more than once")
+            .possible_values(&["victory", "defeat", "abandoned", "unknown"])
+            .multiple(true)
+            .number_of_values(1)
+            .global(true),
+        Arg::with_name("min-realm")
+            .long("min-realm")
+            .value_name("N")
+            .help("Only runs that reached at least realm N")
+            .global(true),
+        Arg::with_name("max-realm")
+            .long("max-realm")
+            .value_name("N")
+            .help("Only runs that reached at most realm N")
+            .global(true),
+        Arg::with_name("mode")
+            .long("mode")
+            .value_name("MODE")
+            .help("Only runs in this game mode, either normal, trial or weekly, or as it's shown, like \"Trial: Fire and Ice\". Can be given more than once")
+            .multiple(true)
+            .number_of_values(1)
+            .global(true),
+        Arg::with_name("runs")
+            .long("runs")
+            .value_name("FROM-TO")
+            .help("Only runs whose folder is numbered from FROM to TO, either of which can be left out, like 20- or -35")
+            .allow_hyphen_values(true)
+            .global(true),
+        Arg::with_name("since")
+            .long("since")
+            .value_name("YYYY-MM-DD")
+            .help("Only runs last played on or after this date (UTC), going by the stats files' modification times")
+            .global(true),
+        Arg::with_name("until")
+            .long("until")
+            .value_name("YYYY-MM-DD")
+            .help("Only runs last played on or before this date (UTC)")
+            .global(true),
+        Arg::with_name("purchased")
+            .long("purchased")
+            .value_name("NAME")
+            .help("Only runs that bought this spell or skill, can be given more than once")
+            .multiple(true)
+            .number_of_values(1)
+            .global(true),
+        Arg::with_name("cast")
+            .long("cast")
+            .value_name("NAME")
+            .help("Only runs that cast this spell, can be given more than once")
+            .multiple(true)
+            .number_of_values(1)
+            .global(true),
+        Arg::with_name("where")
+            .long("where")
+            .value_name("EXPR")
+            .help("Only runs matching this expression, like 'outcome == victory && realms >= 20 && bought(\"Chain Lightning\")'. See the README for what it can use")
+            .allow_hyphen_values(true)
+            .global(true),
+    ]
+}
+
+// exits if a filter option doesn't make sense
+fn run_filter(matches : &ArgMatches) -> RunFilter
+{
+    let values = |name| -> Vec<String> { matches.values_of(name).into_iter().flatten().map(String::from).collect() };
+
+    let number = |name| -> Option<i64> {
+        matches.value_of(name).map(|value| value.parse().unwrap_or_else(|_| {
+            eprintln!("error: --{} must be a whole number", name);
+            process::exit(EXIT_USAGE);
+        }))
+    };
+
+    let date = |name| -> Option<SystemTime> {
+        matches.value_of(name).map(|value| parse_date(value).unwrap_or_else(|| {
+            eprintln!("error: --{} must be a date like 2021-03-14", name);
+            process::exit(EXIT_USAGE);
+        }))
+    };
+
+    let (min_id, max_id) = match matches.value_of("runs") {
+        None => (None, None),
+        Some(range) => {
+            let bound = |bound : &str| -> Option<u64> {
+                if bound.is_empty() {
+                    return None;
+                }
+                Some(bound.parse().unwrap_or_else(|_| {
+                    eprintln!("error: --runs must be a range of run numbers like 20-35, 20- or -35");
+                    process::exit(EXIT_USAGE);
+                }))
+            };
+
+            match range.split_once('-') {
+                Some((from, to)) => (bound(from), bound(to)),
+                // a single run number is a range of one
+                None => (bound(range), bound(range)),
             }
+        },
+    };
+
+    RunFilter {
+        outcomes : values("outcome").iter().map(|outcome| match outcome.as_str() {
+            "victory" => Outcome::Victory,
+            "defeat" => Outcome::Defeat,
+            "abandoned" => Outcome::Abandoned,
+            _ => Outcome::Unknown,
+        }).collect(),
+        min_realm : number("min-realm"),
+        max_realm : number("max-realm"),
+        game_modes : values("mode"),
+        min_id,
+        max_id,
+        since : date("since"),
+        // the until date is included, so the range ends at the start of the next day
+        until : date("until").map(|until| until + Duration::from_secs(24 * 60 * 60)),
+        purchased : values("purchased"),
+        cast : values("cast"),
+        query : matches.value_of("where").map(|source| Query::parse(source).unwrap_or_else(|err| {
+            print_query_error(source, &err);
+            process::exit(EXIT_USAGE);
+        })),
+    }
+}
+
+// shown like a parse error, with a caret under the problem
+fn print_query_error(source : &str, err : &QueryError)
+{
+    eprintln!("error: {}", err.message);
+    eprintln!("  --> --where:{}", err.position + 1);
+    eprintln!("   |");
+    eprintln!("   | {}", source);
+    eprintln!("   | {}^", " ".repeat(err.position));
+}
+
+fn run_arg() -> Arg<'static, 'static>
+{
+    Arg::with_name("RUN")
+        .help("A run's folder name inside the saves folder, like 21, or a path to it")
+        .required(true)
+        .index(1)
+}
+
+// exits if the saves can't be read
+fn read_all_runs(save_location_list : &[PathBuf], options : &ReadOptions, problems : &mut ReadProblems) -> MergedRuns
+{
+    match read_save_folders(save_location_list, options, problems) {
+        Err(err) => {
+            print_problem("error", &err);
+            process::exit(exit_code(&err));
+        },
+        Ok(r) => r
+    }
+}
+
+// exits if the run can't be found or read
+fn read_single_run(save_location_list : &[PathBuf], run : &str, options : &ReadOptions, problems : &mut ReadProblems) -> Run
+{
+    let mut run_folders = find_run_folders(save_location_list, run);
+
+    let run_folder = match run_folders.len() {
+        0 => {
+            eprintln!("error: couldn't find run {} in any save folder", run);
+            process::exit(EXIT_INVALID_DIRECTORY);
+        },
+        1 => run_folders.remove(0),
+        _ => {
+            eprintln!("error: run {} is in more than one save folder, give the path of the one you mean:", run);
+            for run_folder in run_folders {
+                eprintln!("  {}", run_folder.display());
+            }
+            process::exit(EXIT_USAGE);
+        },
+    };
+
+    match read_save(run_folder.clone().into_boxed_path(), options, problems) {
+        Err(err) => {
+            print_problem("error", &err);
+            process::exit(exit_code(&err));
+        },
+        Ok(realms) => {
+            let mut run = generate_run_report(realms);
+            run.id = run_id(&run_folder);
+            run.name = run_name(&run_folder);
+            run
         }
-        run.turns_taken_run = cmp::max(run.turns_taken_run, realm.turns_taken_run);
-        run.spell_casts = run.spell_casts.merge_add(realm.spell_casts);
-        run.damage_to_enemies = run.damage_to_enemies.merge_add(realm.damage_to_enemies);
-        run.damage_to_wizard = run.damage_to_wizard.merge_add(realm.damage_to_wizard);
-        run.items_used = run.items_used.merge_add(realm.items_used);
-    }
-    
-    if run.outcome == Outcome::Victory && run.realms < 25
-    {
-        run.outcome = Outcome::Abandoned;
     }
-    
-    run
 }
 
 
+// only exits if something failed, so main carries on to return normally otherwise
+fn print_problems_and_exit(problems : &ReadProblems)
+{
+    if !problems.warnings.is_empty() {
+        eprintln!();
+        eprintln!("{} malformed line(s) skipped:", problems.warnings.len());
+        for warning in problems.warnings.iter() {
+            print_problem("warning", warning);
+        }
+    }
+
+    if !problems.failures.is_empty() {
+        eprintln!();
+        eprintln!("{} file(s) or folder(s) failed to read:", problems.failures.len());
+        for err in problems.failures.iter() {
+            print_problem("error", err);
+        }
 
-fn get_mean_per_turn(map : &HashMap<String, usize>, turns : i64) -> f64
+        process::exit(problems.failures.iter().map(exit_code).max().unwrap());
+    }
+}
+
+// parse errors are shown compiler-style, with the offending line and a caret under the problem
+fn print_problem(label : &str, err : &ReadSaveError)
 {
-    let mut running_sum = 0;
-    for (_key, value) in map.iter() {
-        running_sum += value;
+    match err {
+        ReadSaveError::Gather(_, GatherError { kind, location : Some(loc) }) => {
+            let gutter = " ".repeat(loc.line_number.to_string().len());
+            // the column is a byte offset, the caret needs to line up in characters
+            let caret_offset = loc.line.get(..loc.column).map_or(loc.column, |pre| pre.chars().count());
+
+            eprintln!("{}: {}", label, kind);
+            eprintln!("{}--> {}:{}:{}", gutter, loc.path.display(), loc.line_number, loc.column + 1);
+            eprintln!("{} |", gutter);
+            eprintln!("{} | {}", loc.line_number, loc.line);
+            eprintln!("{} | {}^", gutter, " ".repeat(caret_offset));
+            eprintln!();
+        },
+        _ => eprintln!("{}: {}", label, err),
     }
-    
-    running_sum as f64 / (turns as f64)
 }
 
-fn print_top_ten(map : &HashMap<String, usize>)
+fn parse_count_arg(matches : &ArgMatches, name : &str) -> usize
 {
-    let mut v = Vec::with_capacity(map.len());
-    
-    for (key, _) in map.iter() {
-        v.push(key.clone())
+    match matches.value_of(name).unwrap().parse() {
+        Ok(count) => count,
+        Err(_) => {
+            eprintln!("error: --{} must be a whole number", name);
+            process::exit(EXIT_USAGE);
+        }
     }
-    
-    v.sort_by(|a, b| { map.get(b).unwrap().cmp(map.get(a).unwrap()) });
-    
-    for key in v.iter().take(10) {
-        println!("{} - {}", key, map.get(key).unwrap());
+}
+
+// clap already exits with 1 for bad arguments it can catch itself
+const EXIT_USAGE : i32 = 1;
+const EXIT_INVALID_DIRECTORY : i32 = 2;
+const EXIT_IO : i32 = 3;
+const EXIT_ILLEGAL_FILENAME : i32 = 4;
+const EXIT_PARSE : i32 = 5;
+
+// with --keep-going and several failures we exit with the highest of these
+fn exit_code(err : &ReadSaveError) -> i32
+{
+    match err {
+        ReadSaveError::InvalidDirectory(_) => EXIT_INVALID_DIRECTORY,
+        ReadSaveError::Io(_, _) | ReadSaveError::Gather(_, GatherError { kind : GatherErrorKind::IO(_), .. }) => EXIT_IO,
+        ReadSaveError::IllegalFilename(_) => EXIT_ILLEGAL_FILENAME,
+        ReadSaveError::Gather(_, _) => EXIT_PARSE,
     }
 }
@@ -0,0 +1,123 @@
+//! The summary as Markdown, with every section a table whose columns line up in plain text too.
+
+use riftwizardstats::MergedRuns;
+
+use crate::summary::{summaries, Section, Summary, TopList};
+use crate::text::ReportOptions;
+
+pub fn print_merged_runs_markdown(merged_runs : MergedRuns, report_options : &ReportOptions)
+{
+    println!("# Rift Wizard Stats");
+
+    for summary in summaries(merged_runs, report_options) {
+        print_summary(&summary);
+    }
+}
+
+fn print_summary(summary : &Summary)
+{
+    println!();
+    println!("## {}", summary.title);
+
+    println!();
+    println!("### Outcomes");
+    let outcomes = [("won", summary.num_victory), ("lost", summary.num_defeat), ("abandoned", summary.num_abandoned), ("unknown", summary.num_unknown)];
+    let rows = outcomes.iter()
+        .map(|(outcome, runs)| vec![outcome.to_string(), runs.to_string(), format!("{:.1}%", *runs as f64 * 100.0 / summary.total_runs.max(1) as f64)])
+        .chain(std::iter::once(vec!["total".to_string(), summary.total_runs.to_string(), String::new()]))
+        .collect();
+    print_table(&[("outcome", false), ("runs", true), ("share", true)], rows);
+
+    for section in summary.sections.iter() {
+        match section {
+            Section::Top(list) => print_top_list(list),
+            Section::WinRates { heading, purchases } => {
+                println!();
+                println!("### {}", heading);
+                let rows = purchases.iter()
+                    .map(|outcomes| vec![
+                        outcomes.name.clone(),
+                        format!("{:.1}%", outcomes.win_rate() * 100.0),
+                        outcomes.runs.to_string(),
+                        outcomes.num_victory.to_string(),
+                        outcomes.num_defeat.to_string(),
+                        outcomes.num_abandoned.to_string(),
+                    ])
+                    .collect();
+                print_table(&[("purchase", false), ("won", true), ("runs", true), ("victories", true), ("defeats", true), ("abandoned", true)], rows);
+            },
+        }
+    }
+}
+
+fn print_top_list(list : &TopList)
+{
+    println!();
+    println!("### {}", list.heading);
+
+    let rows = list.rows.iter()
+        .map(|row| {
+            let mut cells = vec![row.name.clone(), row.total.to_string()];
+            if list.rates {
+                cells.push(format!("{:.3}", row.per_turn));
+                cells.push(format!("{:.2}", row.per_run));
+            }
+            cells
+        })
+        .collect();
+
+    if list.rates {
+        print_table(&[("name", false), ("total", true), ("per turn", true), ("per run", true)], rows);
+    } else {
+        print_table(&[("name", false), ("total", true)], rows);
+    }
+
+    if !list.means.is_empty() {
+        println!();
+        // two trailing spaces are a line break in Markdown
+        let means : Vec<_> = list.means.iter().map(|(label, mean)| format!("{}: {}", capitalize(label), mean)).collect();
+        println!("{}", means.join("  \n"));
+    }
+}
+
+fn capitalize(text : &str) -> String
+{
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+// columns are (header, right aligned), and padded so the table reads as a table before it's rendered too
+fn print_table(columns : &[(&str, bool)], rows : Vec<Vec<String>>)
+{
+    let rows : Vec<Vec<String>> = rows.into_iter().map(|row| row.iter().map(|cell| escape(cell)).collect()).collect();
+
+    let widths : Vec<usize> = columns.iter().enumerate()
+        .map(|(i, (header, _))| {
+            let widest = rows.iter().map(|row| row[i].chars().count()).max().unwrap_or(0);
+            // the divider needs at least three characters
+            widest.max(header.chars().count()).max(3)
+        })
+        .collect();
+
+    let line = |cells : Vec<String>| format!("| {} |", cells.join(" | "));
+    let pad = |text : &str, width : usize, right : bool| {
+        let padding = " ".repeat(width - text.chars().count());
+        if right { padding + text } else { text.to_string() + &padding }
+    };
+
+    println!();
+    println!("{}", line(columns.iter().zip(widths.iter()).map(|((header, right), width)| pad(header, *width, *right)).collect()));
+    println!("{}", line(columns.iter().zip(widths.iter())
+        .map(|((_, right), width)| if *right { "-".repeat(width - 1) + ":" } else { "-".repeat(*width) })
+        .collect()));
+
+    for row in rows.iter() {
+        println!("{}", line(row.iter().zip(columns.iter().zip(widths.iter())).map(|(cell, ((_, right), width))| pad(cell, *width, *right)).collect()));
+    }
+}
+
+// a | in a name would end the cell early
+fn escape(text : &str) -> String
+{
+    text.replace('|', "\\|")
+}
@@ -1,9 +1,38 @@
 use std::fs::*;
-use crate::*;
+use std::io;
+use std::fmt;
+use std::error;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::collections::HashSet;
+
 use crate::types::*;
 
+/// A stats file that couldn't be parsed, and where in it that happened.
+#[derive(Debug)]
+pub struct GatherError
+{
+    pub kind : GatherErrorKind,
+    /// `None` if the file couldn't be opened at all
+    pub location : Option<Location>,
+}
+
+/// A position in a stats file.
+#[derive(Debug)]
+pub struct Location
+{
+    pub path : PathBuf,
+    /// 1-based
+    pub line_number : usize,
+    /// the raw text of the line, empty if we ran off the end of the file
+    pub line : String,
+    /// 0-based byte offset into `line` of the problem
+    pub column : usize,
+}
+
 #[derive(Debug)]
-pub enum GatherError
+pub enum GatherErrorKind
 {
     IO(io::Error),
     ParseInt(std::num::ParseIntError),
@@ -12,10 +41,75 @@ pub enum GatherError
     LineTooShortToContainExpected(String),
     MissingExpectedPostfix(String),
     MissingExpectedDividerLine,
+    /// a line inside a section without the separator between its name and count
+    MissingSeparator(String),
+    /// a line where a section title should be that isn't one
+    UnexpectedLine,
     NotVictoryOrDefeat,
     SectionEnded
 }
 
+impl GatherErrorKind {
+    /// Attaches the column of the problem; `parse` fills in the rest of the location.
+    fn at(self, column : usize) -> GatherError
+    {
+        GatherError {
+            kind : self,
+            location : Some(Location { path : PathBuf::new(), line_number : 0, line : String::new(), column }),
+        }
+    }
+}
+
+impl fmt::Display for GatherErrorKind {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            GatherErrorKind::IO(err) => write!(f, "{}", err),
+            GatherErrorKind::ParseInt(err) => write!(f, "expected a number: {}", err),
+            GatherErrorKind::ReachedEOF => write!(f, "file ended early"),
+            GatherErrorKind::MissingExpectedPrefix(prefix) => write!(f, "expected a line starting with \"{}\"", prefix),
+            GatherErrorKind::LineTooShortToContainExpected(expected) => write!(f, "line too short to contain \"{}\"", expected),
+            GatherErrorKind::MissingExpectedPostfix(postfix) => write!(f, "expected a line ending with \"{}\"", postfix),
+            GatherErrorKind::MissingExpectedDividerLine => write!(f, "expected a divider line"),
+            GatherErrorKind::MissingSeparator(separator) => write!(f, "expected \"{}\" between the name and the count", separator),
+            GatherErrorKind::UnexpectedLine => write!(f, "expected a section title ending with \":\""),
+            GatherErrorKind::NotVictoryOrDefeat => write!(f, "outcome is neither VICTORY nor DEFEAT"),
+            GatherErrorKind::SectionEnded => write!(f, "section ended early"),
+        }
+    }
+}
+
+impl fmt::Display for GatherError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        match &self.location {
+            Some(loc) => write!(f, "{}:{}:{}: {}", loc.path.display(), loc.line_number, loc.column + 1, self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl error::Error for GatherError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)>
+    {
+        match &self.kind {
+            GatherErrorKind::IO(err) => Some(err),
+            GatherErrorKind::ParseInt(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// The lines of a stats file, keeping track of where we are for error messages.
+struct StatsLines
+{
+    path : PathBuf,
+    line_number : usize,
+    lines : io::Lines<io::BufReader<File>>,
+    /// `Some` in lenient mode, collecting the bad lines we skipped
+    warnings : Option<Vec<GatherError>>,
+}
+
 #[derive(Debug)]
 enum SectionTitle
 {
@@ -24,54 +118,46 @@ enum SectionTitle
     DamageToWizard,
     ItemsUsed,
     Purchases,
+    /// a title we don't recognise, without its trailing colon
+    Other(String),
+    /// the end of the file
     None
 }
 
 pub fn gather_stats_from_file(filename : Box<Path>) -> Result<Realm, GatherError>
 {
-    println!("In file {}", filename.display());
+    gather_stats(filename, None).map(|(realm, _)| realm)
+}
+
+/// Like `gather_stats_from_file`, but a malformed line inside a section is skipped instead of
+/// failing the whole file, and a malformed turn count reads as 0. Only a bad `Realm` or `Outcome`
+/// line still fails it. The skipped lines are returned alongside the `Realm` as warnings.
+pub fn gather_stats_from_file_lenient(filename : Box<Path>) -> Result<(Realm, Vec<GatherError>), GatherError>
+{
+    gather_stats(filename, Some(Vec::new())).map(|(realm, warnings)| (realm, warnings.unwrap_or_default()))
+}
 
-    let mut line_iter = match read_lines(filename) {
-            Ok(val) => val,
-            Err(err) => return Err(GatherError::IO(err))
+fn gather_stats(filename : Box<Path>, warnings : Option<Vec<GatherError>>) -> Result<(Realm, Option<Vec<GatherError>>), GatherError>
+{
+    let mut line_iter = match read_lines(&filename) {
+            Ok(val) => StatsLines { path : filename.to_path_buf(), line_number : 0, lines : val, warnings },
+            Err(err) => return Err(GatherError { kind : GatherErrorKind::IO(err), location : None })
         };
 
     let mut realm = Realm { ..Default::default() };
 
-    realm.realm_number = match expect_prefix_read_int(&mut line_iter, "Realm ") {
-        Ok(val) => val,
-        Err(err) => return Err(err)
-    };
+    realm.realm_number = expect_prefix_read_int(&mut line_iter, "Realm ")?;
     
-    realm.outcome = match read_victory(&mut line_iter) {
-        Ok(val) => val,
-        Err(err) => return Err(err)
-    };
+    let (outcome, game_mode) = read_victory(&mut line_iter)?;
+    realm.outcome = outcome;
+    realm.game_mode = game_mode;
     
-    match expect_exact_line(&mut line_iter, "") {
-        Ok(_) => (),
-        Err(err) => return Err(err)
-    };
-    
-    match expect_exact_line(&mut line_iter, "Turns taken:") {
-        Ok(_) => (),
-        Err(err) => return Err(err)
-    };
-    
-    realm.turns_taken_realm = match expect_postfix_read_int(&mut line_iter, " (L)") {
-        Ok(val) => val,
-        Err(err) => return Err(err)
-    };
-    
-    realm.turns_taken_run = match expect_postfix_read_int(&mut line_iter, " (G)") {
-        Ok(val) => val,
-        Err(err) => return Err(err)
-    };
-    
-    match expect_exact_line(&mut line_iter, "") {
-        Ok(_) => (),
-        Err(err) => return Err(err)
-    };
+    // only the realm number and outcome are worth failing the file over, a bad turn count just reads as 0
+    or_skip_bad_line(&mut line_iter, (), |lines| expect_exact_line(lines, ""))?;
+    or_skip_bad_line(&mut line_iter, (), |lines| expect_exact_line(lines, "Turns taken:"))?;
+    realm.turns_taken_realm = or_skip_bad_line(&mut line_iter, 0, |lines| expect_postfix_read_int(lines, " (L)"))?;
+    realm.turns_taken_run = or_skip_bad_line(&mut line_iter, 0, |lines| expect_postfix_read_int(lines, " (G)"))?;
+    or_skip_bad_line(&mut line_iter, (), |lines| expect_exact_line(lines, ""))?;
     
     loop {
         match read_section_title(&mut line_iter) {
@@ -103,8 +189,14 @@ pub fn gather_stats_from_file(filename : Box<Path>) -> Result<Realm, GatherError
                             Ok(val) => Some(val),
                             Err(err) => return Err(err)
                         },
-                    SectionTitle::None => return Ok(realm),
+                    SectionTitle::Other(title) => {
+                        let section = read_generic_section_until_empty(&mut line_iter)?;
+                        realm.other_sections.get_or_insert_with(HashMap::new).insert(title, section);
+                    },
+                    SectionTitle::None => return Ok((realm, line_iter.warnings)),
                 },
+            // skipping it keeps every section after it, where stopping would lose them without a trace
+            Err(err @ GatherError { kind : GatherErrorKind::UnexpectedLine, .. }) => skip_bad_line(&mut line_iter, err)?,
             Err(err) => return Err(err)
         }
     }
@@ -113,16 +205,11 @@ pub fn gather_stats_from_file(filename : Box<Path>) -> Result<Realm, GatherError
 }
 
 fn read_section_title(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>)
+    line_iter : &mut StatsLines)
     -> Result<SectionTitle, GatherError>
 {
     match parse(line_iter,
         |line| {
-            if line.len() < 1
-            {
-                return Err(GatherError::LineTooShortToContainExpected(String::from("section title")))
-            }
-            
             if line.starts_with("Spell Casts:") {
                 return Ok(SectionTitle::SpellCasts);
             }
@@ -143,19 +230,24 @@ fn read_section_title(
                 return Ok(SectionTitle::Purchases);
             }
             
-            Ok(SectionTitle::None)
+            // anything else that looks like a title is probably from a newer game version or a mod
+            if let Some(title) = line.strip_suffix(':') {
+                return Ok(SectionTitle::Other(title.to_string()));
+            }
+            
+            Err(GatherErrorKind::UnexpectedLine.at(0))
         }
     ) {
         Ok(val) => Ok(val),
-        Err(e) => match e {
-            GatherError::SectionEnded | GatherError::ReachedEOF => return Ok(SectionTitle::None),
-            _ => return Err(e)
+        Err(e) => match e.kind {
+            GatherErrorKind::SectionEnded | GatherErrorKind::ReachedEOF => Ok(SectionTitle::None),
+            _ => Err(e)
         }
     }
 }
 
 fn read_hashset_until_empty(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>)
+    line_iter : &mut StatsLines)
     -> Result<HashSet<String>, GatherError>
 {
     let mut result = HashSet::new();
@@ -163,26 +255,72 @@ fn read_hashset_until_empty(
     loop {
         let line_result = parse(line_iter,
             |line| {
-                if line.len() < 1
+                if line.is_empty()
                 {
-                    return Err(GatherError::SectionEnded);
+                    Err(GatherErrorKind::SectionEnded.at(0))
                 } else {
-                    return Ok(line);
+                    Ok(line.to_string())
                 }
             }
         );
         
         match line_result {
             Ok(key) => result.insert(key),
-            Err(e) => match e {
-                    GatherError::SectionEnded | GatherError::ReachedEOF => return Ok(result),
+            Err(e) => match e.kind {
+                    GatherErrorKind::SectionEnded | GatherErrorKind::ReachedEOF => return Ok(result),
                     _ => return Err(e) }
         };
     }
 }
 
+fn read_generic_section_until_empty(
+    line_iter : &mut StatsLines)
+    -> Result<Section, GatherError>
+{
+    let mut lines = Vec::new();
+    
+    loop {
+        let line_result = parse(line_iter,
+            |line| {
+                if line.is_empty()
+                {
+                    Err(GatherErrorKind::SectionEnded.at(0))
+                } else {
+                    Ok(line.to_string())
+                }
+            }
+        );
+        
+        match line_result {
+            Ok(line) => lines.push(line),
+            Err(e) => match e.kind {
+                    GatherErrorKind::SectionEnded | GatherErrorKind::ReachedEOF => break,
+                    _ => return Err(e) }
+        };
+    }
+    
+    // we don't know the layout ahead of time, so try both count layouts the known sections use
+    let prefix_counts : Option<HashMap<String, usize>> = lines.iter().map(|line| {
+            let index = line.find(": ")?;
+            let count = line[index + 2..].parse::<usize>().ok()?;
+            Some((line[..index].to_string(), count))
+        }).collect();
+    
+    let postfix_counts : Option<HashMap<String, usize>> = lines.iter().map(|line| {
+            let index = line.find(' ')?;
+            let count = line[..index].parse::<usize>().ok()?;
+            Some((line[index + 1..].to_string(), count))
+        }).collect();
+    
+    Ok(match (prefix_counts, postfix_counts) {
+        _ if lines.is_empty() => Section::Lines(lines),
+        (Some(counts), _) | (None, Some(counts)) => Section::Counts(counts),
+        (None, None) => Section::Lines(lines),
+    })
+}
+
 fn read_hashmap_prefix_until_empty(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>)
+    line_iter : &mut StatsLines)
     -> Result<HashMap<String, usize>, GatherError>
 {
     let mut result = HashMap::new();
@@ -190,10 +328,16 @@ fn read_hashmap_prefix_until_empty(
     loop {
         let line_result = parse(line_iter,
             |line| {
+                if line.is_empty()
+                {
+                    return Err(GatherErrorKind::SectionEnded.at(0))
+                }
+                
+                // a line with no separator is a broken entry, not the end of the section
                 let index = match line.find(": ")
                 {
                     Some(i) => i,
-                    None => return Err(GatherError::SectionEnded)
+                    None => return Err(GatherErrorKind::MissingSeparator(String::from(": ")).at(0))
                 };
                 
                 // there's better ways to do this
@@ -203,22 +347,23 @@ fn read_hashmap_prefix_until_empty(
                 match post.parse::<usize>()
                 {
                     Ok(val) => Ok((String::from(key), val)),
-                    Err(err) => Err(GatherError::ParseInt(err))
+                    Err(err) => Err(GatherErrorKind::ParseInt(err).at(index + 2))
                 }
             }
         );
         
         match line_result {
-            Ok((key, value)) => result.insert(key, value),
-            Err(e) => match e {
-                        GatherError::SectionEnded | GatherError::ReachedEOF => return Ok(result),
+            Ok((key, value)) => { result.insert(key, value); },
+            Err(e) => match e.kind {
+                        GatherErrorKind::SectionEnded | GatherErrorKind::ReachedEOF => return Ok(result),
+                        GatherErrorKind::ParseInt(_) | GatherErrorKind::MissingSeparator(_) => skip_bad_line(line_iter, e)?,
                         _ => return Err(e) }
         };
     }
 }
 
 fn read_hashmap_postfix_until_empty(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>)
+    line_iter : &mut StatsLines)
     -> Result<HashMap<String, usize>, GatherError>
 {
     let mut result = HashMap::new();
@@ -226,10 +371,16 @@ fn read_hashmap_postfix_until_empty(
     loop {
         let line_result = parse(line_iter,
             |line| {
+                if line.is_empty()
+                {
+                    return Err(GatherErrorKind::SectionEnded.at(0))
+                }
+                
+                // a line with no separator is a broken entry, not the end of the section
                 let index = match line.find(' ')
                 {
                     Some(i) => i,
-                    None => return Err(GatherError::SectionEnded)
+                    None => return Err(GatherErrorKind::MissingSeparator(String::from(" ")).at(0))
                 };
                 
                 
@@ -240,22 +391,56 @@ fn read_hashmap_postfix_until_empty(
                 match pre.parse::<usize>()
                 {
                     Ok(val) => Ok((String::from(key), val)),
-                    Err(err) => Err(GatherError::ParseInt(err))
+                    Err(err) => Err(GatherErrorKind::ParseInt(err).at(0))
                 }
             }
         );
         
         match line_result {
-            Ok((key, value)) => result.insert(key, value),
-            Err(e) => match e {
-                        GatherError::SectionEnded | GatherError::ReachedEOF => return Ok(result),
+            Ok((key, value)) => { result.insert(key, value); },
+            Err(e) => match e.kind {
+                        GatherErrorKind::SectionEnded | GatherErrorKind::ReachedEOF => return Ok(result),
+                        GatherErrorKind::ParseInt(_) | GatherErrorKind::MissingSeparator(_) => skip_bad_line(line_iter, e)?,
                         _ => return Err(e) }
         };
     }
 }
 
+/// In lenient mode a bad line is recorded as a warning and skipped, otherwise it's an error.
+fn skip_bad_line(
+    line_iter : &mut StatsLines,
+    err : GatherError)
+    -> Result<(), GatherError>
+{
+    match &mut line_iter.warnings {
+        Some(warnings) => {
+            warnings.push(err);
+            Ok(())
+        },
+        None => Err(err)
+    }
+}
+
+/// Reads one line with `f`, falling back to `fallback` if the line is bad and we're lenient.
+/// Running out of lines or failing to read them is still an error.
+fn or_skip_bad_line<T, F>(
+    line_iter : &mut StatsLines,
+    fallback : T,
+    f : F)
+    -> Result<T, GatherError>
+    where F : FnOnce(&mut StatsLines) -> Result<T, GatherError>
+{
+    match f(line_iter) {
+        Ok(val) => Ok(val),
+        Err(e) => match e.kind {
+            GatherErrorKind::ReachedEOF | GatherErrorKind::IO(_) => Err(e),
+            _ => skip_bad_line(line_iter, e).map(|_| fallback)
+        }
+    }
+}
+
 fn expect_exact_line(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>,
+    line_iter : &mut StatsLines,
     expected : &str)
     -> Result<(), GatherError>
 {
@@ -263,11 +448,11 @@ fn expect_exact_line(
         |line| {
             if expected.len() != line.len()
             {
-                return Err(GatherError::LineTooShortToContainExpected(expected.to_string()))
+                return Err(GatherErrorKind::LineTooShortToContainExpected(expected.to_string()).at(0))
             }
             
             if !line.starts_with(expected) {
-                return Err(GatherError::MissingExpectedDividerLine);
+                return Err(GatherErrorKind::MissingExpectedDividerLine.at(0));
             }
             
             Ok(())
@@ -276,14 +461,14 @@ fn expect_exact_line(
 }
 
 fn expect_prefix_read_int(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>,
+    line_iter : &mut StatsLines,
     prefix : &str)
     -> Result<i64, GatherError>
 {
     parse(line_iter,
         |line| {
             if !line.starts_with(prefix) {
-                return Err(GatherError::MissingExpectedPrefix(prefix.to_string()));
+                return Err(GatherErrorKind::MissingExpectedPrefix(prefix.to_string()).at(0));
             }
             
             let (_,post) = line.split_at(prefix.len());
@@ -292,14 +477,14 @@ fn expect_prefix_read_int(
             match post.parse::<i64>()
             {
                 Ok(val) => Ok(val),
-                Err(err) => Err(GatherError::ParseInt(err))
+                Err(err) => Err(GatherErrorKind::ParseInt(err).at(prefix.len()))
             }
         }
     )
 }
 
 fn expect_postfix_read_int(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>,
+    line_iter : &mut StatsLines,
     postfix : &str)
     -> Result<i64, GatherError>
 {
@@ -310,34 +495,34 @@ fn expect_postfix_read_int(
             
             if len < post_len
             {
-                return Err(GatherError::LineTooShortToContainExpected(postfix.to_string()))
+                return Err(GatherErrorKind::LineTooShortToContainExpected(postfix.to_string()).at(len))
             }
             
             
             let (pre,post) = line.split_at(len - post_len);
             
             if !post.starts_with(postfix) {
-                return Err(GatherError::MissingExpectedPostfix(postfix.to_string()));
+                return Err(GatherErrorKind::MissingExpectedPostfix(postfix.to_string()).at(len - post_len));
             }
             
             match pre.parse::<i64>()
             {
                 Ok(val) => Ok(val),
-                Err(err) => Err(GatherError::ParseInt(err))
+                Err(err) => Err(GatherErrorKind::ParseInt(err).at(0))
             }
         }
     )
 }
 
 fn read_victory(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>)
-    -> Result<Outcome, GatherError>
+    line_iter : &mut StatsLines)
+    -> Result<(Outcome, GameMode), GatherError>
 {
-    let parser_fn = |line : String| {
+    let parser_fn = |line : &str| {
             let prefix = "Outcome: ";
             
             if !line.starts_with(prefix) {
-                return Err(GatherError::MissingExpectedPrefix(prefix.to_string()));
+                return Err(GatherErrorKind::MissingExpectedPrefix(prefix.to_string()).at(0));
             }
             
             let (_,post) = line.split_at(prefix.len());
@@ -352,32 +537,58 @@ fn read_victory(
                 return Ok(Outcome::Victory);
             }
             
-            return Err(GatherError::NotVictoryOrDefeat);
+            Err(GatherErrorKind::NotVictoryOrDefeat.at(prefix.len()))
         };
     
     
     // if the prefix is missing, it might've been a challenge mode or a weekly, so we try twice in that case
     match parse(line_iter, parser_fn) {
-        Err(GatherError::MissingExpectedPrefix(_)) => parse(line_iter, parser_fn),
-        other => other
+        Ok(outcome) => Ok((outcome, GameMode::Normal)),
+        Err(GatherError { kind : GatherErrorKind::MissingExpectedPrefix(_), location : Some(loc) }) => {
+            let game_mode = read_game_mode(&loc.line);
+            parse(line_iter, parser_fn).map(|outcome| (outcome, game_mode))
+        },
+        Err(err) => Err(err)
     }
 }
 
+// a weekly's line is `Weekly Run` and its identifier, a trial's is just the trial's name
+fn read_game_mode(line : &str) -> GameMode
+{
+    if let Some(id) = line.strip_prefix("Weekly Run") {
+        return GameMode::Weekly(id.trim_start_matches(':').trim().to_string());
+    }
+    
+    GameMode::Trial(line.to_string())
+}
+
 fn parse<T, F>(
-    line_iter : &mut impl Iterator<Item=Result<String, std::io::Error>>,
+    line_iter : &mut StatsLines,
     f : F)
     -> Result<T, GatherError>
-    where F : FnOnce(String) -> Result<T, GatherError>
+    where F : FnOnce(&str) -> Result<T, GatherError>
 {
-    let line = match line_iter.next() {
-        None => return Err(GatherError::ReachedEOF),
+    line_iter.line_number += 1;
+    
+    let (line, result) = match line_iter.lines.next() {
+        None => (String::new(), Err(GatherErrorKind::ReachedEOF.at(0))),
         Some(val) => match val {
-            Ok(inner) => inner,
-            Err(err) => return Err(GatherError::IO(err))
+            Ok(inner) => {
+                let result = f(&inner);
+                (inner, result)
+            },
+            Err(err) => (String::new(), Err(GatherErrorKind::IO(err).at(0)))
         }
     };
     
-    f(line)
+    result.map_err(|mut err| {
+        if let Some(loc) = &mut err.location {
+            loc.path = line_iter.path.clone();
+            loc.line_number = line_iter.line_number;
+            loc.line = line;
+        }
+        err
+    })
 }
 
 
@@ -386,3 +597,143 @@ where P: AsRef<Path>, {
     let file = File::open(filename)?;
     Ok(io::BufReader::new(file).lines())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER : &str = "Realm 3\nOutcome: DEFEAT\n\nTurns taken:\n12 (L)\n40 (G)\n\n";
+
+    // each test gets its own file, since they run in parallel
+    fn stats_file(name : &str, body : &str) -> Box<Path>
+    {
+        let path = std::env::temp_dir().join(format!("riftwizardstats-parse-{}-{}.txt", std::process::id(), name));
+        std::fs::write(&path, format!("{}{}", HEADER, body)).unwrap();
+        path.into_boxed_path()
+    }
+
+    #[test]
+    fn reads_every_section()
+    {
+        let path = stats_file("every", "Spell Casts:\nFireball: 3\n\nDamage to Enemies:\n40 Fireball\n\nPurchases:\nFireball\n");
+        let realm = gather_stats_from_file(path).unwrap();
+
+        assert_eq!(realm.realm_number, 3);
+        assert_eq!(realm.outcome, Outcome::Defeat);
+        assert_eq!(realm.turns_taken_run, 40);
+        assert_eq!(realm.spell_casts.unwrap()["Fireball"], 3);
+        assert_eq!(realm.damage_to_enemies.unwrap()["Fireball"], 40);
+        assert!(realm.purchases.unwrap().contains("Fireball"));
+    }
+
+    #[test]
+    fn missing_separator_fails_strict()
+    {
+        let path = stats_file("strict", "Spell Casts:\nFireball 3\nMagic Missile: 5\n\nItems Used:\nMana Potion: 1\n");
+        let err = gather_stats_from_file(path).unwrap_err();
+
+        assert!(matches!(err.kind, GatherErrorKind::MissingSeparator(_)));
+        assert_eq!(err.location.unwrap().line_number, 9);
+    }
+
+    #[test]
+    fn lenient_skips_bad_lines_and_keeps_later_sections()
+    {
+        let path = stats_file("lenient", "Spell Casts:\nFireball 3\nMagic Missile: 5\n\nDamage to Enemies:\nlots Fireball\n12\n30 Magic Missile\n\nItems Used:\nMana Potion: 1\n");
+        let (realm, warnings) = gather_stats_from_file_lenient(path).unwrap();
+
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(realm.spell_casts.unwrap().get("Magic Missile"), Some(&5));
+        assert_eq!(realm.damage_to_enemies.unwrap().get("Magic Missile"), Some(&30));
+        assert_eq!(realm.items_used.unwrap().get("Mana Potion"), Some(&1));
+    }
+
+    #[test]
+    fn lenient_reads_bad_turn_counts_as_zero()
+    {
+        let path = std::env::temp_dir().join(format!("riftwizardstats-parse-{}-turns.txt", std::process::id()));
+        std::fs::write(&path, "Realm 25\nOutcome: VICTORY\n\nTurns taken:\n3O (L)\n400 (G)\n\nSpell Casts:\nFireball: 3\n").unwrap();
+
+        assert!(matches!(gather_stats_from_file(path.clone().into_boxed_path()).unwrap_err().kind, GatherErrorKind::ParseInt(_)));
+
+        let (realm, warnings) = gather_stats_from_file_lenient(path.into_boxed_path()).unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].location.as_ref().unwrap().line_number, 5);
+        assert_eq!(realm.outcome, Outcome::Victory);
+        assert_eq!(realm.turns_taken_realm, 0);
+        assert_eq!(realm.turns_taken_run, 400);
+        assert_eq!(realm.spell_casts.unwrap()["Fireball"], 3);
+    }
+
+    fn header_file(name : &str, header : &str) -> Box<Path>
+    {
+        let path = std::env::temp_dir().join(format!("riftwizardstats-parse-{}-{}.txt", std::process::id(), name));
+        std::fs::write(&path, format!("Realm 2\n{}Outcome: VICTORY\n\nTurns taken:\n31 (L)\n58 (G)\n\nSpell Casts:\nFireball: 3\n", header)).unwrap();
+        path.into_boxed_path()
+    }
+
+    #[test]
+    fn reads_the_game_mode_line()
+    {
+        let realm = gather_stats_from_file(header_file("normal", "")).unwrap();
+        assert_eq!(realm.game_mode, GameMode::Normal);
+
+        let realm = gather_stats_from_file(header_file("trial", "Fire and Ice\n")).unwrap();
+        assert_eq!(realm.game_mode, GameMode::Trial("Fire and Ice".to_string()));
+        assert_eq!(realm.outcome, Outcome::Victory);
+        assert_eq!(realm.turns_taken_run, 58);
+        assert_eq!(realm.spell_casts.unwrap()["Fireball"], 3);
+
+        let realm = gather_stats_from_file(header_file("weekly", "Weekly Run: 10/04/2021\n")).unwrap();
+        assert_eq!(realm.game_mode, GameMode::Weekly("10/04/2021".to_string()));
+
+        let realm = gather_stats_from_file(header_file("weekly-number", "Weekly Run 142\n")).unwrap();
+        assert_eq!(realm.game_mode, GameMode::Weekly("142".to_string()));
+    }
+
+    #[test]
+    fn only_one_game_mode_line()
+    {
+        let err = gather_stats_from_file(header_file("two-modes", "Fire and Ice\nWeekly Run 142\n")).unwrap_err();
+
+        assert!(matches!(err.kind, GatherErrorKind::MissingExpectedPrefix(_)));
+        assert_eq!(err.location.unwrap().line_number, 3);
+    }
+
+    #[test]
+    fn lines_that_arent_titles_are_malformed()
+    {
+        let body = "Kills\nGoblin: 4\n\nDamage to Wizard:\n12 Goblin\n";
+
+        let err = gather_stats_from_file(stats_file("untitled-strict", body)).unwrap_err();
+        assert!(matches!(err.kind, GatherErrorKind::UnexpectedLine));
+        assert_eq!(err.location.unwrap().line_number, 8);
+
+        let (realm, warnings) = gather_stats_from_file_lenient(stats_file("untitled-lenient", body)).unwrap();
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(realm.damage_to_wizard.unwrap()["Goblin"], 12);
+        assert!(realm.other_sections.is_none());
+    }
+
+    #[test]
+    fn unknown_sections_become_counts_or_lines()
+    {
+        let path = stats_file("generic", "Kills:\nGoblin: 4\nSpriggan: 2\n\nShrines:\n3 Fire Shrine\n\nNotes:\nsomething happened\n");
+        let realm = gather_stats_from_file(path).unwrap();
+        let other_sections = realm.other_sections.unwrap();
+
+        match &other_sections["Kills"] {
+            Section::Counts(counts) => assert_eq!(counts["Goblin"], 4),
+            other => panic!("expected counts, got {:?}", other),
+        }
+        match &other_sections["Shrines"] {
+            Section::Counts(counts) => assert_eq!(counts["Fire Shrine"], 3),
+            other => panic!("expected counts, got {:?}", other),
+        }
+        match &other_sections["Notes"] {
+            Section::Lines(lines) => assert_eq!(lines, &vec!["something happened".to_string()]),
+            other => panic!("expected lines, got {:?}", other),
+        }
+    }
+}
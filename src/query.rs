@@ -0,0 +1,674 @@
+//! A small expression language for picking runs, e.g.
+//!
+//! ```text
+//! outcome == victory && realms >= 20 && bought("Chain Lightning") && damage_to_enemies("Fire Imp") > 500
+//! ```
+//!
+//! An expression is parsed and type checked once by [`Query::parse`], then [`Query::matches`] runs it
+//! against each [`Run`].
+//!
+//! Fields of the run:
+//!
+//! * `outcome` - compared with `victory`, `defeat`, `abandoned` or `unknown`
+//! * `realms`, `turns` - numbers
+//! * `mode` - compared with text, either `"normal"`, `"trial"`, `"weekly"` or the mode
+//!   as the reports show it, like `"Trial: Fire and Ice"`
+//! * `name` - the run's folder name, like `"21"`, compared with text
+//! * `id` - the run's id, the full path of its folder, compared with text
+//!
+//! Functions, where names ignore case:
+//!
+//! * `bought("Name")`, `cast("Name")` - whether the run bought or cast it
+//! * `spell_casts("Name")`, `damage_to_enemies("Name")`, `damage_to_wizard("Name")`, `items_used("Name")` -
+//!   that number for one name, or the total over every name if called with no name
+//!
+//! Numbers compare with `==`, `!=`, `<`, `<=`, `>` and `>=`, anything else only with `==` and `!=`.
+//! Conditions combine with `&&`, `||`, `!` and parentheses; `&&` binds tighter than `||`.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use crate::filter::mode_matches;
+use crate::types::{GameMode, Outcome, Run};
+
+/// A parsed and type checked expression that picks runs.
+#[derive(Debug, Clone)]
+pub struct Query
+{
+    pub source : String,
+    expr : Expr,
+}
+
+/// What went wrong in an expression, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError
+{
+    pub message : String,
+    /// how many characters into the expression, so a caret can be put under it
+    pub position : usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl error::Error for QueryError {}
+
+impl Query {
+    pub fn parse(source : &str) -> Result<Query, QueryError>
+    {
+        // positions are byte offsets until here, which is no good for pointing at anything after a non-ASCII character
+        Query::parse_expr(source).map_err(|err| {
+            let position = source.get(..err.position).map_or(err.position, |before| before.chars().count());
+            QueryError { position, ..err }
+        })
+    }
+
+    fn parse_expr(source : &str) -> Result<Query, QueryError>
+    {
+        let tokens = lex(source)?;
+        let mut parser = Parser { tokens, next : 0 };
+
+        let expr = parser.or()?;
+
+        let end = parser.peek();
+        if end.kind != TokenKind::End {
+            return Err(error(end.position, format!("expected `&&`, `||` or the end of the expression, found {}", end.kind)));
+        }
+
+        match check(&expr)? {
+            Type::Bool => Ok(Query { source : source.to_string(), expr }),
+            other => Err(error(expr.position, format!("the expression needs to be true or false, but this is {}", other))),
+        }
+    }
+
+    pub fn matches(&self, run : &Run) -> bool
+    {
+        evaluate(&self.expr, run) == Value::Bool(true)
+    }
+}
+
+fn error(position : usize, message : String) -> QueryError
+{
+    QueryError { message, position }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind
+{
+    Number(i64),
+    Text(String),
+    Name(String),
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Compare(Comparison),
+    End,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            TokenKind::Number(n) => write!(f, "`{}`", n),
+            TokenKind::Text(text) => write!(f, "\"{}\"", text),
+            TokenKind::Name(name) => write!(f, "`{}`", name),
+            TokenKind::Open => write!(f, "`(`"),
+            TokenKind::Close => write!(f, "`)`"),
+            TokenKind::And => write!(f, "`&&`"),
+            TokenKind::Or => write!(f, "`||`"),
+            TokenKind::Not => write!(f, "`!`"),
+            TokenKind::Compare(comparison) => write!(f, "`{}`", comparison),
+            TokenKind::End => write!(f, "the end of the expression"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison
+{
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        let symbol = match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token
+{
+    kind : TokenKind,
+    position : usize,
+}
+
+fn lex(source : &str) -> Result<Vec<Token>, QueryError>
+{
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let mut followed_by = |expected : char| chars.next_if(|(_, next)| *next == expected).is_some();
+
+        let kind = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            '&' if followed_by('&') => TokenKind::And,
+            '|' if followed_by('|') => TokenKind::Or,
+            '=' if followed_by('=') => TokenKind::Compare(Comparison::Equal),
+            '!' if followed_by('=') => TokenKind::Compare(Comparison::NotEqual),
+            '!' => TokenKind::Not,
+            '<' if followed_by('=') => TokenKind::Compare(Comparison::LessOrEqual),
+            '<' => TokenKind::Compare(Comparison::Less),
+            '>' if followed_by('=') => TokenKind::Compare(Comparison::GreaterOrEqual),
+            '>' => TokenKind::Compare(Comparison::Greater),
+            '&' | '|' | '=' => return Err(error(position, format!("`{}` on its own isn't anything, did you mean `{}{}`?", c, c, c))),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => text.extend(chars.next().map(|(_, c)| c)),
+                        Some((_, c)) => text.push(c),
+                        None => return Err(error(position, "this text is missing its closing `\"`".to_string())),
+                    }
+                }
+                TokenKind::Text(text)
+            },
+            '0'..='9' => {
+                let mut digits = c.to_string();
+                while let Some((_, digit)) = chars.next_if(|(_, next)| next.is_ascii_digit()) {
+                    digits.push(digit);
+                }
+                match digits.parse() {
+                    Ok(number) => TokenKind::Number(number),
+                    Err(_) => return Err(error(position, "this number is too big".to_string())),
+                }
+            },
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some((_, next)) = chars.next_if(|(_, next)| next.is_alphanumeric() || *next == '_') {
+                    name.push(next);
+                }
+                TokenKind::Name(name)
+            },
+            _ => return Err(error(position, format!("unexpected `{}`", c))),
+        };
+
+        tokens.push(Token { kind, position });
+    }
+
+    tokens.push(Token { kind : TokenKind::End, position : source.len() });
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field
+{
+    Outcome,
+    Realms,
+    Turns,
+    Mode,
+    Name,
+    Id,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function
+{
+    Bought,
+    Cast,
+    SpellCasts,
+    DamageToEnemies,
+    DamageToWizard,
+    ItemsUsed,
+}
+
+#[derive(Debug, Clone)]
+enum ExprKind
+{
+    Number(i64),
+    Text(String),
+    Bool(bool),
+    Outcome(Outcome),
+    Field(Field),
+    /// with the name given to it, if any
+    Call(Function, Option<String>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, Comparison, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+struct Expr
+{
+    kind : ExprKind,
+    /// where it starts in the source, for errors
+    position : usize,
+}
+
+struct Parser
+{
+    tokens : Vec<Token>,
+    next : usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token
+    {
+        &self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> Token
+    {
+        let token = self.tokens[self.next].clone();
+        // the end token stays put, so running off the end keeps finding it
+        if token.kind != TokenKind::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, kind : TokenKind, what : &str) -> Result<Token, QueryError>
+    {
+        let token = self.advance();
+        if token.kind == kind {
+            Ok(token)
+        } else {
+            Err(error(token.position, format!("expected {}, found {}", what, token.kind)))
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError>
+    {
+        let mut left = self.and()?;
+
+        while self.peek().kind == TokenKind::Or {
+            self.advance();
+            let right = self.and()?;
+            let position = left.position;
+            left = Expr { kind : ExprKind::Or(Box::new(left), Box::new(right)), position };
+        }
+
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError>
+    {
+        let mut left = self.not()?;
+
+        while self.peek().kind == TokenKind::And {
+            self.advance();
+            let right = self.not()?;
+            let position = left.position;
+            left = Expr { kind : ExprKind::And(Box::new(left), Box::new(right)), position };
+        }
+
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, QueryError>
+    {
+        if self.peek().kind == TokenKind::Not {
+            let position = self.advance().position;
+            let inner = self.not()?;
+            return Ok(Expr { kind : ExprKind::Not(Box::new(inner)), position });
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, QueryError>
+    {
+        let left = self.primary()?;
+
+        let comparison = match self.peek().kind {
+            TokenKind::Compare(comparison) => comparison,
+            _ => return Ok(left),
+        };
+        self.advance();
+
+        let right = self.primary()?;
+        let position = left.position;
+
+        Ok(Expr { kind : ExprKind::Compare(Box::new(left), comparison, Box::new(right)), position })
+    }
+
+    fn primary(&mut self) -> Result<Expr, QueryError>
+    {
+        let token = self.advance();
+        let position = token.position;
+
+        let kind = match token.kind {
+            TokenKind::Number(number) => ExprKind::Number(number),
+            TokenKind::Text(text) => ExprKind::Text(text),
+            TokenKind::Open => {
+                let inner = self.or()?;
+                self.expect(TokenKind::Close, "`)`")?;
+                return Ok(inner);
+            },
+            TokenKind::Name(name) => return self.name(name, position),
+            other => return Err(error(position, format!("expected a value, found {}", other))),
+        };
+
+        Ok(Expr { kind, position })
+    }
+
+    fn name(&mut self, name : String, position : usize) -> Result<Expr, QueryError>
+    {
+        let function = match name.as_str() {
+            "bought" => Some(Function::Bought),
+            "cast" => Some(Function::Cast),
+            "spell_casts" => Some(Function::SpellCasts),
+            "damage_to_enemies" => Some(Function::DamageToEnemies),
+            "damage_to_wizard" => Some(Function::DamageToWizard),
+            "items_used" => Some(Function::ItemsUsed),
+            _ => None,
+        };
+
+        if let Some(function) = function {
+            self.expect(TokenKind::Open, &format!("`(` after `{}`", name))?;
+
+            let argument = self.advance();
+            let argument = match argument.kind {
+                TokenKind::Text(text) => {
+                    self.expect(TokenKind::Close, "`)`")?;
+                    Some(text)
+                },
+                TokenKind::Close => None,
+                other => return Err(error(argument.position, format!("`{}` takes a name in quotes, found {}", name, other))),
+            };
+
+            if argument.is_none() && (function == Function::Bought || function == Function::Cast) {
+                return Err(error(position, format!("`{}` needs a name, like {}(\"Fireball\")", name, name)));
+            }
+
+            return Ok(Expr { kind : ExprKind::Call(function, argument), position });
+        }
+
+        let kind = match name.as_str() {
+            "outcome" => ExprKind::Field(Field::Outcome),
+            "realms" => ExprKind::Field(Field::Realms),
+            "turns" => ExprKind::Field(Field::Turns),
+            "mode" => ExprKind::Field(Field::Mode),
+            "name" => ExprKind::Field(Field::Name),
+            "id" => ExprKind::Field(Field::Id),
+            "victory" => ExprKind::Outcome(Outcome::Victory),
+            "defeat" => ExprKind::Outcome(Outcome::Defeat),
+            "abandoned" => ExprKind::Outcome(Outcome::Abandoned),
+            "unknown" => ExprKind::Outcome(Outcome::Unknown),
+            "true" => ExprKind::Bool(true),
+            "false" => ExprKind::Bool(false),
+            _ => return Err(error(position, format!(
+                "unknown name `{}`, expected a field (outcome, realms, turns, mode, name, id), an outcome (victory, defeat, abandoned, unknown) \
+                 or a function (bought, cast, spell_casts, damage_to_enemies, damage_to_wizard, items_used)", name))),
+        };
+
+        Ok(Expr { kind, position })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type
+{
+    Number,
+    Text,
+    Bool,
+    Outcome,
+    Mode,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        let name = match self {
+            Type::Number => "a number",
+            Type::Text => "text",
+            Type::Bool => "true or false",
+            Type::Outcome => "an outcome",
+            Type::Mode => "a game mode",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+fn check(expr : &Expr) -> Result<Type, QueryError>
+{
+    let expect_bool = |inner : &Expr, what : &str| -> Result<(), QueryError> {
+        match check(inner)? {
+            Type::Bool => Ok(()),
+            other => Err(error(inner.position, format!("{} needs true or false on both sides, but this is {}", what, other))),
+        }
+    };
+
+    match &expr.kind {
+        ExprKind::Number(_) => Ok(Type::Number),
+        ExprKind::Text(_) => Ok(Type::Text),
+        ExprKind::Bool(_) => Ok(Type::Bool),
+        ExprKind::Outcome(_) => Ok(Type::Outcome),
+        ExprKind::Field(Field::Outcome) => Ok(Type::Outcome),
+        ExprKind::Field(Field::Realms) | ExprKind::Field(Field::Turns) => Ok(Type::Number),
+        ExprKind::Field(Field::Mode) => Ok(Type::Mode),
+        ExprKind::Field(Field::Name) | ExprKind::Field(Field::Id) => Ok(Type::Text),
+        ExprKind::Call(Function::Bought, _) | ExprKind::Call(Function::Cast, _) => Ok(Type::Bool),
+        ExprKind::Call(_, _) => Ok(Type::Number),
+        ExprKind::Not(inner) => match check(inner)? {
+            Type::Bool => Ok(Type::Bool),
+            other => Err(error(inner.position, format!("`!` needs true or false, but this is {}", other))),
+        },
+        ExprKind::And(left, right) => {
+            expect_bool(left, "`&&`")?;
+            expect_bool(right, "`&&`")?;
+            Ok(Type::Bool)
+        },
+        ExprKind::Or(left, right) => {
+            expect_bool(left, "`||`")?;
+            expect_bool(right, "`||`")?;
+            Ok(Type::Bool)
+        },
+        ExprKind::Compare(left, comparison, right) => {
+            let (left_type, right_type) = (check(left)?, check(right)?);
+
+            let comparable = match (left_type, right_type) {
+                (Type::Mode, Type::Text) | (Type::Text, Type::Mode) => true,
+                // a mode only compares with text, the way --mode matches it
+                (Type::Mode, Type::Mode) => false,
+                (left_type, right_type) => left_type == right_type,
+            };
+
+            if !comparable {
+                return Err(error(right.position, format!("can't compare {} with {}", left_type, right_type)));
+            }
+
+            let equality = *comparison == Comparison::Equal || *comparison == Comparison::NotEqual;
+            if !equality && left_type != Type::Number {
+                return Err(error(left.position, format!("`{}` only compares numbers, but this is {}", comparison, left_type)));
+            }
+
+            Ok(Type::Bool)
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value
+{
+    Number(i64),
+    Text(String),
+    Bool(bool),
+    Outcome(Outcome),
+    Mode(GameMode),
+}
+
+// the types were checked when the query was parsed, so a mismatch here can't happen
+fn evaluate(expr : &Expr, run : &Run) -> Value
+{
+    match &expr.kind {
+        ExprKind::Number(number) => Value::Number(*number),
+        ExprKind::Text(text) => Value::Text(text.clone()),
+        ExprKind::Bool(b) => Value::Bool(*b),
+        ExprKind::Outcome(outcome) => Value::Outcome(*outcome),
+        ExprKind::Field(Field::Outcome) => Value::Outcome(run.outcome),
+        ExprKind::Field(Field::Realms) => Value::Number(run.realms),
+        ExprKind::Field(Field::Turns) => Value::Number(run.turns_taken_run),
+        ExprKind::Field(Field::Mode) => Value::Mode(run.game_mode.clone()),
+        ExprKind::Field(Field::Name) => Value::Text(run.name.clone()),
+        ExprKind::Field(Field::Id) => Value::Text(run.id.clone()),
+        ExprKind::Call(Function::Bought, name) => {
+            let name = name.as_deref().unwrap_or_default();
+            Value::Bool(run.purchases.iter().flatten().any(|purchase| purchase.name.eq_ignore_ascii_case(name)))
+        },
+        ExprKind::Call(Function::Cast, name) => Value::Bool(count(&run.spell_casts, name) > 0),
+        ExprKind::Call(Function::SpellCasts, name) => Value::Number(count(&run.spell_casts, name)),
+        ExprKind::Call(Function::DamageToEnemies, name) => Value::Number(count(&run.damage_to_enemies, name)),
+        ExprKind::Call(Function::DamageToWizard, name) => Value::Number(count(&run.damage_to_wizard, name)),
+        ExprKind::Call(Function::ItemsUsed, name) => Value::Number(count(&run.items_used, name)),
+        ExprKind::Not(inner) => Value::Bool(evaluate(inner, run) != Value::Bool(true)),
+        ExprKind::And(left, right) => Value::Bool(evaluate(left, run) == Value::Bool(true) && evaluate(right, run) == Value::Bool(true)),
+        ExprKind::Or(left, right) => Value::Bool(evaluate(left, run) == Value::Bool(true) || evaluate(right, run) == Value::Bool(true)),
+        ExprKind::Compare(left, comparison, right) => {
+            let result = match (evaluate(left, run), evaluate(right, run)) {
+                (Value::Number(left), Value::Number(right)) => match comparison {
+                    Comparison::Equal => left == right,
+                    Comparison::NotEqual => left != right,
+                    Comparison::Less => left < right,
+                    Comparison::LessOrEqual => left <= right,
+                    Comparison::Greater => left > right,
+                    Comparison::GreaterOrEqual => left >= right,
+                },
+                // a mode matches text the way --mode does
+                (Value::Mode(game_mode), Value::Text(mode)) | (Value::Text(mode), Value::Mode(game_mode)) => {
+                    mode_matches(&mode, &game_mode) == (*comparison == Comparison::Equal)
+                },
+                (left, right) => (left == right) == (*comparison == Comparison::Equal),
+            };
+            Value::Bool(result)
+        },
+    }
+}
+
+// one name's count ignoring case, or every name's if there's no name
+fn count(map : &Option<HashMap<String, usize>>, name : &Option<String>) -> i64
+{
+    let total : usize = map.iter().flatten()
+        .filter(|(key, _)| name.as_ref().is_none_or(|name| key.eq_ignore_ascii_case(name)))
+        .map(|(_, value)| value)
+        .sum();
+
+    total as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Purchase;
+
+    fn run() -> Run
+    {
+        Run {
+            name : "21".to_string(),
+            realms : 12,
+            outcome : Outcome::Defeat,
+            game_mode : GameMode::Trial("Fire and Ice".to_string()),
+            turns_taken_run : 300,
+            damage_to_enemies : Some(HashMap::from([("Fire Imp".to_string(), 600), ("Fireball".to_string(), 400)])),
+            purchases : Some(vec![Purchase { name : "Chain Lightning".to_string(), outcome : Outcome::Defeat, realm : 2 }]),
+            ..Default::default()
+        }
+    }
+
+    fn matches(source : &str) -> bool
+    {
+        Query::parse(source).unwrap().matches(&run())
+    }
+
+    fn parse_error(source : &str) -> QueryError
+    {
+        Query::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or()
+    {
+        // read as true || (false && false)
+        assert!(matches("realms == 12 || realms == 1 && turns == 1"));
+        assert!(!matches("(realms == 12 || realms == 1) && turns == 1"));
+        assert!(!matches("!realms == 12 || outcome == victory"));
+        assert!(matches("!(realms == 1) && outcome == defeat"));
+    }
+
+    #[test]
+    fn type_errors_point_at_the_problem()
+    {
+        let err = parse_error("realms > 3 && outcome == 3");
+        assert_eq!(err.message, "can't compare an outcome with a number");
+        assert_eq!(err.position, 25);
+
+        let err = parse_error("outcome < victory");
+        assert_eq!(err.message, "`<` only compares numbers, but this is an outcome");
+        assert_eq!(err.position, 0);
+
+        let err = parse_error("realms && bought(\"Fireball\")");
+        assert_eq!(err.message, "`&&` needs true or false on both sides, but this is a number");
+        assert_eq!(err.position, 0);
+
+        let err = parse_error("turns");
+        assert_eq!(err.message, "the expression needs to be true or false, but this is a number");
+    }
+
+    #[test]
+    fn positions_count_characters_not_bytes()
+    {
+        let err = parse_error("name == \"é\" && realms == victory");
+        assert_eq!(err.position, 25);
+        assert_eq!(err.to_string(), "can't compare a number with an outcome at column 26");
+    }
+
+    #[test]
+    fn mode_compares_with_text_like_the_mode_option()
+    {
+        assert!(matches("mode == \"trial\""));
+        assert!(matches("mode == \"Trial: Fire and Ice\""));
+        assert!(matches("\"TRIAL\" == mode"));
+        assert!(matches("mode != \"weekly\""));
+        assert!(!matches("mode == \"Fire and Ice\""));
+
+        assert_eq!(parse_error("mode == 3").message, "can't compare a game mode with a number");
+        assert_eq!(parse_error("mode == mode").message, "can't compare a game mode with a game mode");
+        assert_eq!(parse_error("mode < \"trial\"").message, "`<` only compares numbers, but this is a game mode");
+    }
+
+    #[test]
+    fn names_ignore_case()
+    {
+        assert!(matches("bought(\"chain lightning\")"));
+        assert!(!matches("bought(\"Fireball\")"));
+        assert!(matches("damage_to_enemies(\"FIRE IMP\") == 600"));
+        assert!(matches("damage_to_enemies() == 1000"));
+        assert!(matches("cast(\"Fireball\") == false"));
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::types::{GameMode, MergedRuns, Outcome, Run};
+
+/// How the runs that bought a particular spell or skill turned out.
+#[derive(Default, Debug, Clone)]
+pub struct PurchaseOutcomes
+{
+    pub name : String,
+    pub runs : usize,
+    pub num_victory : usize,
+    pub num_defeat : usize,
+    pub num_abandoned : usize,
+    pub num_unknown : usize,
+}
+
+impl PurchaseOutcomes {
+    /// Victories out of the runs that were played to the end, so abandoned and unknown runs don't count either way.
+    pub fn win_rate(&self) -> f64
+    {
+        let finished = self.num_victory + self.num_defeat;
+        
+        if finished == 0 {
+            return 0.0;
+        }
+        
+        self.num_victory as f64 / finished as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PurchaseSort
+{
+    WinRate,
+    Runs,
+    Name,
+}
+
+/// Win rate for every spell or skill bought in at least `min_runs` runs, sorted by `sort`.
+///
+/// Ties are broken by number of runs and then by name, so the order is stable between calls.
+pub fn purchase_outcomes(merged_runs : &MergedRuns, min_runs : usize, sort : PurchaseSort) -> Vec<PurchaseOutcomes>
+{
+    let mut by_name : HashMap<&str, PurchaseOutcomes> = HashMap::new();
+    
+    for (purchase, count) in merged_runs.purchases.iter().flatten() {
+        let outcomes = by_name.entry(&purchase.name).or_insert_with(|| PurchaseOutcomes {
+            name : purchase.name.clone(),
+            ..Default::default() });
+        
+        outcomes.runs += count;
+        
+        match purchase.outcome {
+            Outcome::Victory => outcomes.num_victory += count,
+            Outcome::Defeat => outcomes.num_defeat += count,
+            Outcome::Abandoned => outcomes.num_abandoned += count,
+            Outcome::Unknown => outcomes.num_unknown += count,
+        }
+    }
+    
+    let mut result : Vec<_> = by_name.into_values().filter(|outcomes| outcomes.runs >= min_runs).collect();
+    
+    result.sort_by(|a, b| {
+        let by_runs = b.runs.cmp(&a.runs).then_with(|| a.name.cmp(&b.name));
+        
+        match sort {
+            PurchaseSort::WinRate => b.win_rate().total_cmp(&a.win_rate()).then(by_runs),
+            PurchaseSort::Runs => by_runs,
+            PurchaseSort::Name => a.name.cmp(&b.name),
+        }
+    });
+    
+    result
+}
+
+/// How many runs ended in defeat in each realm, for the runs kept in `merged_runs.runs`.
+pub fn deaths_by_realm(merged_runs : &MergedRuns) -> BTreeMap<i64, usize>
+{
+    let mut deaths = BTreeMap::new();
+
+    for run in merged_runs.runs.iter().flatten().filter(|run| run.outcome == Outcome::Defeat) {
+        // realms are numbered from 1, so the last one reached is where the run ended
+        let last_realm = run.realm_stats.iter().flatten().map(|realm| realm.realm_number).max().unwrap_or(run.realms);
+        *deaths.entry(last_realm).or_insert(0) += 1;
+    }
+
+    deaths
+}
+
+/// Damage dealt and taken in one realm number, summed over every run that played it.
+#[derive(Default, Debug, Clone)]
+pub struct RealmDamage
+{
+    pub realm : i64,
+    /// how many runs played this realm
+    pub runs : usize,
+    pub damage_dealt : usize,
+    pub damage_taken : usize,
+}
+
+impl RealmDamage {
+    pub fn mean_dealt(&self) -> f64
+    {
+        self.damage_dealt as f64 / self.runs as f64
+    }
+
+    pub fn mean_taken(&self) -> f64
+    {
+        self.damage_taken as f64 / self.runs as f64
+    }
+}
+
+/// Damage dealt and taken for every realm number any run reached, in realm order,
+/// for the runs kept in `merged_runs.runs`.
+pub fn damage_by_realm(merged_runs : &MergedRuns) -> Vec<RealmDamage>
+{
+    let mut by_realm : BTreeMap<i64, RealmDamage> = BTreeMap::new();
+
+    for realm in merged_runs.runs.iter().flatten().flat_map(|run| run.realm_stats.iter().flatten()) {
+        let damage = by_realm.entry(realm.realm_number).or_insert_with(|| RealmDamage { realm : realm.realm_number, ..Default::default() });
+
+        damage.runs += 1;
+        damage.damage_dealt += realm.damage_to_enemies.iter().flat_map(|map| map.values()).sum::<usize>();
+        damage.damage_taken += realm.damage_to_wizard.iter().flat_map(|map| map.values()).sum::<usize>();
+    }
+
+    by_realm.into_values().collect()
+}
+
+/// One name from `spell_casts` and `damage_to_enemies` joined together. Direct damage spells
+/// have the same name in both; anything else, like a summon or a skill, only shows up in one.
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct SpellDamage
+{
+    pub name : String,
+    pub casts : usize,
+    pub damage : usize,
+    /// damage over casts, counting only the runs where the name was both cast and did damage,
+    /// or `None` if there were none
+    pub damage_per_cast : Option<f64>,
+    pub casts_per_turn : f64,
+    /// of all the damage dealt in the runs
+    pub share_of_damage : f64,
+    /// runs where it was cast but did no damage
+    pub runs_cast_only : usize,
+    /// runs where it did damage but was never cast
+    pub runs_damage_only : usize,
+}
+
+/// Every spell's damage per cast over some runs, see [`spell_damage`].
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct SpellDamageReport
+{
+    pub runs : usize,
+    pub turns : i64,
+    pub total_damage : usize,
+    /// most damage first, then most casts, then by name
+    pub spells : Vec<SpellDamage>,
+}
+
+/// Joins each run's spell casts with its damage to enemies by name, then adds the runs up.
+///
+/// The join is per run so a spell cast in one run can't be credited with damage from another.
+pub fn spell_damage<'a>(runs : impl IntoIterator<Item = &'a Run>) -> SpellDamageReport
+{
+    let mut report = SpellDamageReport::default();
+    let mut by_name : HashMap<String, SpellDamage> = HashMap::new();
+    // casts and damage from the runs with both, for damage per cast
+    let mut matched : HashMap<String, (usize, usize)> = HashMap::new();
+
+    for run in runs {
+        report.runs += 1;
+        report.turns += run.turns_taken_run;
+
+        let casts = run.spell_casts.clone().unwrap_or_default();
+        let damage = run.damage_to_enemies.clone().unwrap_or_default();
+
+        for name in casts.keys().chain(damage.keys().filter(|name| !casts.contains_key(*name))) {
+            let run_casts = casts.get(name).copied().unwrap_or(0);
+            let run_damage = damage.get(name).copied().unwrap_or(0);
+
+            let spell = by_name.entry(name.clone()).or_insert_with(|| SpellDamage { name : name.clone(), ..Default::default() });
+            spell.casts += run_casts;
+            spell.damage += run_damage;
+            report.total_damage += run_damage;
+
+            match (run_casts > 0, run_damage > 0) {
+                (true, true) => {
+                    let both = matched.entry(name.clone()).or_insert((0, 0));
+                    both.0 += run_casts;
+                    both.1 += run_damage;
+                },
+                (true, false) => spell.runs_cast_only += 1,
+                (false, true) => spell.runs_damage_only += 1,
+                (false, false) => (),
+            }
+        }
+    }
+
+    let mut spells : Vec<_> = by_name.into_values().collect();
+
+    for spell in spells.iter_mut() {
+        spell.damage_per_cast = matched.get(&spell.name).map(|(casts, damage)| *damage as f64 / *casts as f64);
+        spell.casts_per_turn = spell.casts as f64 / report.turns.max(1) as f64;
+        spell.share_of_damage = spell.damage as f64 / report.total_damage.max(1) as f64;
+    }
+
+    spells.sort_by(|a, b| b.damage.cmp(&a.damage).then(b.casts.cmp(&a.casts)).then_with(|| a.name.cmp(&b.name)));
+    report.spells = spells;
+
+    report
+}
+
+/// One source of damage to the wizard and how much it did.
+#[derive(Debug, Clone, Serialize)]
+pub struct DamageSource
+{
+    pub name : String,
+    pub damage : usize,
+}
+
+/// One lost run and what was hurting the wizard in the realm it ended in.
+#[derive(Debug, Clone, Serialize)]
+pub struct Death
+{
+    /// the run's id and name, see `Run`
+    pub id : String,
+    pub name : String,
+    pub game_mode : GameMode,
+    pub realm : i64,
+    /// the final realm's damage to the wizard, most damage first, empty if the realm's stats are missing
+    pub sources : Vec<DamageSource>,
+}
+
+/// How one enemy figures in the final realms of lost runs.
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct Killer
+{
+    pub name : String,
+    /// lost runs where it did any damage in the final realm
+    pub deaths : usize,
+    /// lost runs where it did the most damage in the final realm
+    pub deadliest_in : usize,
+    /// summed over those final realms
+    pub damage : usize,
+}
+
+/// What killed the wizard in every lost run, see [`deaths`].
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct DeathReport
+{
+    /// in the order the runs were given
+    pub deaths : Vec<Death>,
+    /// deadliest first, by runs where it did the most damage, then runs it hurt in, then damage
+    pub killers : Vec<Killer>,
+}
+
+/// The damage to the wizard in the final realm of every run in `runs` that ended in defeat,
+/// and the enemies in those realms ranked by how deadly they were.
+pub fn deaths<'a>(runs : impl IntoIterator<Item = &'a Run>) -> DeathReport
+{
+    let mut report = DeathReport::default();
+    let mut by_name : HashMap<String, Killer> = HashMap::new();
+
+    for run in runs.into_iter().filter(|run| run.outcome == Outcome::Defeat) {
+        // same as deaths_by_realm, the last realm reached is where the run ended
+        let last_realm = run.realm_stats.iter().flatten().max_by_key(|realm| realm.realm_number);
+
+        let mut sources : Vec<_> = last_realm.and_then(|realm| realm.damage_to_wizard.as_ref()).into_iter().flatten()
+            .map(|(name, damage)| DamageSource { name : name.clone(), damage : *damage })
+            .collect();
+        // ties by name, so the deadliest of equals is always the same one
+        sources.sort_by(|a, b| b.damage.cmp(&a.damage).then_with(|| a.name.cmp(&b.name)));
+
+        for (i, source) in sources.iter().enumerate() {
+            let killer = by_name.entry(source.name.clone()).or_insert_with(|| Killer { name : source.name.clone(), ..Default::default() });
+            killer.deaths += 1;
+            killer.damage += source.damage;
+            if i == 0 {
+                killer.deadliest_in += 1;
+            }
+        }
+
+        report.deaths.push(Death {
+            id : run.id.clone(),
+            name : run.name.clone(),
+            game_mode : run.game_mode.clone(),
+            realm : last_realm.map_or(run.realms, |realm| realm.realm_number),
+            sources,
+        });
+    }
+
+    report.killers = by_name.into_values().collect();
+    report.killers.sort_by(|a, b| {
+        b.deadliest_in.cmp(&a.deadliest_in)
+            .then(b.deaths.cmp(&a.deaths))
+            .then(b.damage.cmp(&a.damage))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Purchase, Realm};
+
+    fn bought(id : &str, outcome : Outcome, purchases : &[&str]) -> Run
+    {
+        Run {
+            id : id.to_string(),
+            name : id.to_string(),
+            outcome,
+            purchases : Some(purchases.iter().map(|name| Purchase { name : name.to_string(), outcome, realm : 1 }).collect()),
+            ..Default::default()
+        }
+    }
+
+    fn merge(runs : Vec<Run>) -> MergedRuns
+    {
+        runs.into_iter().fold(MergedRuns::default(), |merged_runs, run| merged_runs.merge_run(run))
+    }
+
+    fn purchase_runs() -> MergedRuns
+    {
+        merge(vec![
+            bought("1", Outcome::Victory, &["Fireball", "Chain Lightning"]),
+            bought("2", Outcome::Defeat, &["Fireball", "Chain Lightning"]),
+            bought("3", Outcome::Defeat, &["Fireball", "Chain Lightning"]),
+            bought("4", Outcome::Abandoned, &["Fireball", "Blink"]),
+            bought("5", Outcome::Victory, &["Blink", "Icicle"]),
+            bought("6", Outcome::Victory, &["Blink", "Icicle"]),
+        ])
+    }
+
+    #[test]
+    fn win_rate_only_counts_finished_runs()
+    {
+        let outcomes = purchase_outcomes(&purchase_runs(), 1, PurchaseSort::Name);
+        let fireball = outcomes.iter().find(|outcomes| outcomes.name == "Fireball").unwrap();
+
+        assert_eq!((fireball.runs, fireball.num_victory, fireball.num_defeat, fireball.num_abandoned), (4, 1, 2, 1));
+        assert!((fireball.win_rate() - 1.0 / 3.0).abs() < 1e-9);
+
+        let blink = outcomes.iter().find(|outcomes| outcomes.name == "Blink").unwrap();
+        assert_eq!(blink.win_rate(), 1.0);
+
+        assert_eq!(PurchaseOutcomes { num_abandoned : 2, ..Default::default() }.win_rate(), 0.0);
+    }
+
+    #[test]
+    fn min_runs_leaves_out_rare_purchases()
+    {
+        let names = |min_runs| -> Vec<String> {
+            purchase_outcomes(&purchase_runs(), min_runs, PurchaseSort::Name).into_iter().map(|outcomes| outcomes.name).collect()
+        };
+
+        assert_eq!(names(3), vec!["Blink", "Chain Lightning", "Fireball"]);
+        assert_eq!(names(4), vec!["Fireball"]);
+        assert!(names(5).is_empty());
+    }
+
+    #[test]
+    fn purchases_sort_with_ties_by_runs_then_name()
+    {
+        let names = |sort| -> Vec<String> {
+            purchase_outcomes(&purchase_runs(), 1, sort).into_iter().map(|outcomes| outcomes.name).collect()
+        };
+
+        assert_eq!(names(PurchaseSort::WinRate), vec!["Blink", "Icicle", "Fireball", "Chain Lightning"]);
+        assert_eq!(names(PurchaseSort::Runs), vec!["Fireball", "Blink", "Chain Lightning", "Icicle"]);
+        assert_eq!(names(PurchaseSort::Name), vec!["Blink", "Chain Lightning", "Fireball", "Icicle"]);
+    }
+
+    fn map(entries : &[(&str, usize)]) -> Option<HashMap<String, usize>>
+    {
+        Some(entries.iter().map(|(name, value)| (name.to_string(), *value)).collect())
+    }
+
+    fn stats_run(turns : i64, casts : &[(&str, usize)], damage : &[(&str, usize)]) -> Run
+    {
+        Run { turns_taken_run : turns, spell_casts : map(casts), damage_to_enemies : map(damage), ..Default::default() }
+    }
+
+    fn spell<'a>(report : &'a SpellDamageReport, name : &str) -> &'a SpellDamage
+    {
+        report.spells.iter().find(|spell| spell.name == name).unwrap()
+    }
+
+    #[test]
+    fn damage_per_cast_only_counts_runs_with_both()
+    {
+        let runs = [
+            stats_run(100, &[("Fireball", 10), ("Blink", 5)], &[("Fireball", 300), ("Wolf", 50)]),
+            stats_run(100, &[("Fireball", 5)], &[]),
+            stats_run(100, &[], &[("Fireball", 100)]),
+        ];
+        let report = spell_damage(&runs);
+
+        assert_eq!((report.runs, report.turns, report.total_damage), (3, 300, 450));
+
+        let fireball = spell(&report, "Fireball");
+        assert_eq!((fireball.casts, fireball.damage), (15, 400));
+        assert_eq!(fireball.damage_per_cast, Some(30.0));
+        assert_eq!((fireball.runs_cast_only, fireball.runs_damage_only), (1, 1));
+        assert_eq!(fireball.casts_per_turn, 0.05);
+        assert!((fireball.share_of_damage - 400.0 / 450.0).abs() < 1e-9);
+
+        let blink = spell(&report, "Blink");
+        assert_eq!(blink.damage_per_cast, None);
+        assert_eq!((blink.runs_cast_only, blink.runs_damage_only, blink.share_of_damage), (1, 0, 0.0));
+
+        let wolf = spell(&report, "Wolf");
+        assert_eq!(wolf.damage_per_cast, None);
+        assert_eq!((wolf.runs_cast_only, wolf.runs_damage_only), (0, 1));
+
+        let names : Vec<_> = report.spells.iter().map(|spell| spell.name.as_str()).collect();
+        assert_eq!(names, vec!["Fireball", "Wolf", "Blink"]);
+    }
+
+    #[test]
+    fn no_runs_is_an_empty_report()
+    {
+        let report = spell_damage(&[]);
+
+        assert_eq!((report.runs, report.total_damage), (0, 0));
+        assert!(report.spells.is_empty());
+    }
+
+    fn final_realm(realm_number : i64, damage : &[(&str, usize)]) -> Realm
+    {
+        Realm { realm_number, damage_to_wizard : map(damage), ..Default::default() }
+    }
+
+    fn ended(id : &str, outcome : Outcome, realms : Vec<Realm>) -> Run
+    {
+        Run { id : id.to_string(), name : id.to_string(), outcome, realms : 7, realm_stats : Some(realms), ..Default::default() }
+    }
+
+    fn death_runs() -> Vec<Run>
+    {
+        vec![
+            // realms out of order, and the dragon wasn't in the realm the run ended in
+            ended("1", Outcome::Defeat, vec![
+                final_realm(1, &[]),
+                final_realm(3, &[("Troll", 30), ("Goblin", 30), ("Imp", 5)]),
+                final_realm(2, &[("Dragon", 500)]),
+            ]),
+            ended("2", Outcome::Defeat, vec![final_realm(2, &[("Troll", 40), ("Imp", 10)])]),
+            ended("3", Outcome::Defeat, vec![final_realm(1, &[("Imp", 100)])]),
+            ended("4", Outcome::Victory, vec![final_realm(25, &[("Dragon", 999)])]),
+            ended("5", Outcome::Defeat, vec![final_realm(4, &[("Ogre", 30)])]),
+            ended("6", Outcome::Defeat, vec![final_realm(4, &[("Bat", 31)])]),
+            ended("7", Outcome::Defeat, Vec::new()),
+        ]
+    }
+
+    #[test]
+    fn each_lost_run_ends_in_its_highest_realm()
+    {
+        let report = deaths(&death_runs());
+
+        let ended_in : Vec<_> = report.deaths.iter().map(|death| (death.name.as_str(), death.realm)).collect();
+        assert_eq!(ended_in, vec![("1", 3), ("2", 2), ("3", 1), ("5", 4), ("6", 4), ("7", 7)]);
+
+        let sources : Vec<_> = report.deaths[0].sources.iter().map(|source| (source.name.as_str(), source.damage)).collect();
+        assert_eq!(sources, vec![("Goblin", 30), ("Troll", 30), ("Imp", 5)]);
+        assert!(report.deaths[5].sources.is_empty());
+    }
+
+    #[test]
+    fn killers_rank_by_deadliest_then_deaths_then_damage_then_name()
+    {
+        let report = deaths(&death_runs());
+
+        let killers : Vec<_> = report.killers.iter()
+            .map(|killer| (killer.name.as_str(), killer.deadliest_in, killer.deaths, killer.damage))
+            .collect();
+
+        assert_eq!(killers, vec![
+            ("Imp", 1, 3, 115),
+            ("Troll", 1, 2, 70),
+            ("Bat", 1, 1, 31),
+            // goblin is the deadliest in run 1 only by name, tied with the troll
+            ("Goblin", 1, 1, 30),
+            ("Ogre", 1, 1, 30),
+        ]);
+    }
+}
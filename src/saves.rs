@@ -0,0 +1,216 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the game keeps its saves on a default Windows Steam install, used when nothing else is found.
+pub const WINDOWS_SAVE_LOCATION : &str = r#"C:\Program Files (x86)\Steam\steamapps\common\Rift Wizard\RiftWizard\saves\"#;
+
+/// Rift Wizard's Steam app id, which names its Proton prefix under `steamapps/compatdata`.
+pub const RIFT_WIZARD_APP_ID : &str = "1271280";
+
+// the saves folder, relative to a Steam library
+const SAVES_IN_LIBRARY : [&str; 5] = ["steamapps", "common", "Rift Wizard", "RiftWizard", "saves"];
+
+/// Every Rift Wizard saves folder we can find in the Steam libraries on this machine,
+/// or just `WINDOWS_SAVE_LOCATION` if there are none.
+pub fn find_save_folders() -> Vec<PathBuf>
+{
+    let mut libraries = Vec::new();
+
+    for steam_root in steam_roots() {
+        libraries.push(steam_root.clone());
+
+        // a missing or unreadable file just means there are no other libraries
+        if let Ok(vdf) = fs::read_to_string(steam_root.join("steamapps").join("libraryfolders.vdf")) {
+            libraries.extend(library_folders(&vdf));
+        }
+    }
+
+    let mut save_folders : Vec<PathBuf> = Vec::new();
+
+    for library in libraries {
+        for save_folder in save_folders_in_library(&library) {
+            // the same library is usually reachable through more than one of the steam roots
+            let save_folder = save_folder.canonicalize().unwrap_or(save_folder);
+
+            if save_folder.is_dir() && !save_folders.contains(&save_folder) {
+                save_folders.push(save_folder);
+            }
+        }
+    }
+
+    if save_folders.is_empty() {
+        save_folders.push(PathBuf::from(WINDOWS_SAVE_LOCATION));
+    }
+
+    save_folders
+}
+
+/// The places Steam is normally installed, whether or not they exist.
+pub fn steam_roots() -> Vec<PathBuf>
+{
+    if cfg!(windows) {
+        return vec![PathBuf::from(r#"C:\Program Files (x86)\Steam"#)];
+    }
+
+    let home = match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home),
+        None => return Vec::new(),
+    };
+
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local").join("share"));
+
+    vec![
+        data_home.join("Steam"),
+        home.join(".steam").join("steam"),
+        home.join(".steam").join("root"),
+        // the flatpak
+        home.join(".var").join("app").join("com.valvesoftware.Steam").join(".local").join("share").join("Steam"),
+    ]
+}
+
+/// Where a library's copy of the game might keep its saves: next to the game itself,
+/// or inside the game's Proton prefix when Proton puts them on a Windows-looking drive.
+pub fn save_folders_in_library(library : &Path) -> Vec<PathBuf>
+{
+    let native : PathBuf = SAVES_IN_LIBRARY.iter().fold(library.to_path_buf(), |path, part| path.join(part));
+
+    let proton_steam = library.join("steamapps").join("compatdata").join(RIFT_WIZARD_APP_ID)
+        .join("pfx").join("drive_c").join("Program Files (x86)").join("Steam");
+    let proton : PathBuf = SAVES_IN_LIBRARY.iter().fold(proton_steam, |path, part| path.join(part));
+
+    vec![native, proton]
+}
+
+/// The library paths listed in the contents of Steam's `steamapps/libraryfolders.vdf`.
+///
+/// Newer files list each library as a block with a `"path"` key, older ones as a numbered key
+/// straight inside `"LibraryFolders"` whose value is the path, so both are accepted.
+pub fn library_folders(vdf : &str) -> Vec<PathBuf>
+{
+    let tokens = vdf_tokens(vdf);
+    let mut libraries = Vec::new();
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match (&tokens[i], tokens.get(i + 1)) {
+            (VdfToken::String(key), Some(VdfToken::String(value))) => {
+                // newer files also have numbered keys in each library's "apps" block, which aren't paths
+                let numbered = !key.is_empty() && key.chars().all(|c| c.is_ascii_digit());
+
+                if key == "path" || (numbered && depth == 1) {
+                    libraries.push(PathBuf::from(value));
+                }
+                i += 2;
+            },
+            (VdfToken::String(_), Some(VdfToken::Open)) => {
+                depth += 1;
+                i += 2;
+            },
+            (VdfToken::Close, _) => {
+                depth -= 1;
+                i += 1;
+            },
+            _ => i += 1,
+        }
+    }
+
+    libraries
+}
+
+#[derive(Debug, PartialEq)]
+enum VdfToken
+{
+    String(String),
+    Open,
+    Close,
+}
+
+// just enough of valve's KeyValues format to pick out the paths
+fn vdf_tokens(vdf : &str) -> Vec<VdfToken>
+{
+    let mut tokens = Vec::new();
+    let mut chars = vdf.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(VdfToken::Open),
+            '}' => tokens.push(VdfToken::Close),
+            '"' => {
+                let mut s = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        // windows paths come escaped, e.g. "D:\\SteamLibrary"
+                        '\\' => s.extend(chars.next()),
+                        _ => s.push(c),
+                    }
+                }
+                tokens.push(VdfToken::String(s));
+            },
+            _ => (),
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_library_blocks_but_not_their_apps()
+    {
+        let vdf = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/me/.local/share/Steam"
+		"label"		""
+		"apps"
+		{
+			"228980"		"283524052"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+		"apps"
+		{
+			"1271280"		"174385621"
+		}
+	}
+}
+"#;
+
+        assert_eq!(library_folders(vdf), vec![PathBuf::from("/home/me/.local/share/Steam"), PathBuf::from("/mnt/games/SteamLibrary")]);
+    }
+
+    #[test]
+    fn reads_old_numbered_paths_with_escapes()
+    {
+        let vdf = r#"
+"LibraryFolders"
+{
+	"TimeNextStatsReport"		"1600000000"
+	"ContentStatsID"		"-123"
+	"1"		"D:\\SteamLibrary"
+	"2"		"E:\\Games\\Steam"
+}
+"#;
+
+        assert_eq!(library_folders(vdf), vec![PathBuf::from(r"D:\SteamLibrary"), PathBuf::from(r"E:\Games\Steam")]);
+    }
+
+    #[test]
+    fn nothing_in_an_empty_or_broken_file()
+    {
+        assert!(library_folders("").is_empty());
+        assert!(library_folders("\"libraryfolders\" { \"0\" { \"path\"").is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+//! The summary's sections, worked out once so each `--format` only has to lay them out.
+
+use riftwizardstats::MergedRuns;
+use riftwizardstats::hashmap::*;
+use riftwizardstats::report::{purchase_outcomes, PurchaseOutcomes};
+
+use crate::text::{get_mean_per_run, get_mean_per_turn, top_keys, ReportOptions};
+
+use std::collections::HashMap;
+
+/// The summary of one game mode, or of all of them.
+pub struct Summary
+{
+    pub title : String,
+    pub num_victory : usize,
+    pub num_defeat : usize,
+    pub num_abandoned : usize,
+    pub num_unknown : usize,
+    pub total_runs : usize,
+    pub sections : Vec<Section>,
+}
+
+pub enum Section
+{
+    Top(TopList),
+    WinRates {
+        heading : String,
+        purchases : Vec<PurchaseOutcomes>,
+    },
+}
+
+/// The biggest entries of one stat map.
+pub struct TopList
+{
+    /// e.g. "Damage to enemies (top 10 sum of all runs)"
+    pub heading : String,
+    pub rows : Vec<TopRow>,
+    /// whether the rows have per turn and per run rates
+    pub rates : bool,
+    /// e.g. ("mean damage per turn", 4.2), shown under the list
+    pub means : Vec<(&'static str, f64)>,
+}
+
+pub struct TopRow
+{
+    pub name : String,
+    pub total : usize,
+    pub per_turn : f64,
+    pub per_run : f64,
+}
+
+/// A summary for each game mode, if there's more than one, then one for all of them.
+pub fn summaries(mut merged_runs : MergedRuns, report_options : &ReportOptions) -> Vec<Summary>
+{
+    let mut by_game_mode : Vec<_> = lazy_init(merged_runs.by_game_mode.take()).into_iter().collect();
+    by_game_mode.sort_by(|a, b| { a.0.cmp(&b.0) });
+
+    let mut summaries = Vec::new();
+
+    // with only one mode the per-mode section would just repeat the totals
+    if by_game_mode.len() > 1 {
+        for (game_mode, mode_runs) in by_game_mode.iter() {
+            summaries.push(summary(game_mode.to_string(), mode_runs, report_options));
+        }
+    }
+
+    summaries.push(summary("All modes".to_string(), &merged_runs, report_options));
+
+    summaries
+}
+
+fn summary(title : String, merged_runs : &MergedRuns, report_options : &ReportOptions) -> Summary
+{
+    let total_runs = merged_runs.num_victory + merged_runs.num_defeat + merged_runs.num_abandoned + merged_runs.num_unknown;
+    let turns = merged_runs.turns_taken;
+    let top = report_options.top;
+
+    let top_list = |heading : String, map : &HashMap<String, usize>, rates : bool, means : Vec<(&'static str, f64)>| {
+        let rows = top_keys(map, top).into_iter()
+            .map(|key| TopRow {
+                name : key.clone(),
+                total : map[key],
+                per_turn : map[key] as f64 / turns as f64,
+                per_run : map[key] as f64 / total_runs as f64,
+            })
+            .collect();
+
+        Section::Top(TopList { heading, rows, rates, means })
+    };
+
+    let dealt = merged_runs.damage_to_enemies.clone().unwrap_or_default();
+    let taken = merged_runs.damage_to_wizard.clone().unwrap_or_default();
+    let casts = merged_runs.spell_casts.clone().unwrap_or_default();
+    let items = merged_runs.items_used.clone().unwrap_or_default();
+
+    let mut sections = vec![
+        top_list(format!("Damage to enemies (top {} sum of all runs)", top), &dealt, false,
+            vec![("mean damage per turn", get_mean_per_turn(&dealt, turns))]),
+        top_list(format!("Damage to wizard (top {} sum of all runs)", top), &taken, false,
+            vec![("mean damage per turn", get_mean_per_turn(&taken, turns))]),
+        top_list(format!("Spell casts (top {} sum of all runs)", top), &casts, true,
+            vec![("mean casts per turn", get_mean_per_turn(&casts, turns)), ("mean casts per run", get_mean_per_run(&casts, total_runs))]),
+        top_list(format!("Items used (top {} sum of all runs)", top), &items, true,
+            vec![("mean items used per turn", get_mean_per_turn(&items, turns)), ("mean items used per run", get_mean_per_run(&items, total_runs))]),
+        top_list(format!("Most purchased (top {} of all runs)", top), &merged_runs.purchase_counts(), false, Vec::new()),
+        Section::WinRates {
+            heading : format!("Win rate by purchase (bought in at least {} runs)", report_options.min_runs),
+            purchases : purchase_outcomes(merged_runs, report_options.min_runs, report_options.purchase_sort),
+        },
+    ];
+
+    // sections the parser didn't recognise, so new data at least shows up somewhere
+    if let Some(other_sections) = &merged_runs.other_sections {
+        let mut titles : Vec<_> = other_sections.keys().collect();
+        titles.sort();
+
+        for title in titles {
+            sections.push(top_list(format!("{} (top {} sum of all runs)", title, top), &other_sections[title], false, Vec::new()));
+        }
+    }
+
+    Summary {
+        title,
+        num_victory : merged_runs.num_victory,
+        num_defeat : merged_runs.num_defeat,
+        num_abandoned : merged_runs.num_abandoned,
+        num_unknown : merged_runs.num_unknown,
+        total_runs,
+        sections,
+    }
+}
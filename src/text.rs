@@ -0,0 +1,272 @@
+//! Plain text output, the default `--format`.
+
+use riftwizardstats::{MergedRuns, ReadProblems, Run, RunSort};
+use riftwizardstats::report::{DeathReport, PurchaseSort, SpellDamageReport};
+
+use std::collections::HashMap;
+
+use crate::summary::{summaries, Section, Summary};
+
+/// What the summary shows, from the `summary` command's options.
+pub struct ReportOptions
+{
+    pub purchase_sort : PurchaseSort,
+    pub min_runs : usize,
+    pub top : usize,
+}
+
+pub fn print_run_list(merged_runs : &MergedRuns, sort : RunSort)
+{
+    println!();
+    println!("{:>8}  {:<9}  {:>6}  {:>6}  mode", "run", "outcome", "realms", "turns");
+    for run in merged_runs.sorted_runs(sort) {
+        println!("{:>8}  {:<9}  {:>6}  {:>6}  {}", run.name, format!("{:?}", run.outcome), run.realms, run.turns_taken_run, run.game_mode);
+    }
+}
+
+pub fn print_run_detail(run : &Run)
+{
+    println!();
+    println!("====================================");
+    println!("RUN {}", run.name);
+    println!("====================================");
+    println!("outcome: {:?}", run.outcome);
+    println!("mode: {}", run.game_mode);
+    println!("realms: {}", run.realms);
+    println!("turns: {}", run.turns_taken_run);
+
+    for realm in run.realm_stats.iter().flatten() {
+        println!();
+        println!("------------------------------------");
+        println!("REALM {} - {:?} - {} turns ({} in the run so far)", realm.realm_number, realm.outcome, realm.turns_taken_realm, realm.turns_taken_run);
+        print_stat_line("spell casts", &realm.spell_casts);
+        print_stat_line("damage dealt", &realm.damage_to_enemies);
+        print_stat_line("damage taken", &realm.damage_to_wizard);
+        print_stat_line("items used", &realm.items_used);
+
+        if let Some(purchases) = &realm.purchases {
+            let mut names : Vec<_> = purchases.iter().map(|name| name.as_str()).collect();
+            names.sort();
+            println!("purchased: {}", names.join(", "));
+        }
+    }
+
+    println!();
+    println!("------------------------------------");
+    println!("WHOLE RUN");
+    print_stat_line("spell casts", &run.spell_casts);
+    print_stat_line("damage dealt", &run.damage_to_enemies);
+    print_stat_line("damage taken", &run.damage_to_wizard);
+    print_stat_line("items used", &run.items_used);
+
+    let purchases : Vec<_> = run.purchases.iter().flatten().map(|purchase| purchase.name.as_str()).collect();
+    println!("purchased: {}", purchases.join(", "));
+}
+
+// one line, biggest first, e.g. "spell casts: Fireball 12, Magic Missile 3"
+fn print_stat_line(label : &str, map : &Option<HashMap<String, usize>>)
+{
+    let map = match map {
+        Some(map) => map,
+        None => return,
+    };
+
+    let entries : Vec<_> = top_keys(map, map.len()).iter()
+        .map(|key| format!("{} {}", key, map[*key]))
+        .collect();
+
+    println!("{}: {}", label, entries.join(", "));
+}
+
+pub fn print_purchase_timeline(run : &Run)
+{
+    let timeline : Vec<_> = run.purchase_timeline().iter()
+        .map(|(realm, names)| format!("Realm {}: {}", realm, names.join(", ")))
+        .collect();
+
+    println!();
+    println!("Run {} ({:?}, {} realms)", run.name, run.outcome, run.realms);
+    println!("{}", timeline.join("; "));
+}
+
+// one column per run, one row per stat
+pub fn print_run_comparison(runs : &[Run])
+{
+    let total = |map : &Option<HashMap<String, usize>>| -> usize { map.iter().flatten().map(|(_, value)| value).sum() };
+
+    let rows : Vec<(&str, Vec<String>)> = vec![
+        ("run", runs.iter().map(|run| run.name.clone()).collect()),
+        ("outcome", runs.iter().map(|run| format!("{:?}", run.outcome)).collect()),
+        ("mode", runs.iter().map(|run| run.game_mode.to_string()).collect()),
+        ("realms", runs.iter().map(|run| run.realms.to_string()).collect()),
+        ("turns", runs.iter().map(|run| run.turns_taken_run.to_string()).collect()),
+        ("damage dealt", runs.iter().map(|run| total(&run.damage_to_enemies).to_string()).collect()),
+        ("damage taken", runs.iter().map(|run| total(&run.damage_to_wizard).to_string()).collect()),
+        ("spell casts", runs.iter().map(|run| total(&run.spell_casts).to_string()).collect()),
+        ("items used", runs.iter().map(|run| total(&run.items_used).to_string()).collect()),
+        ("purchases", runs.iter().map(|run| run.purchases.iter().flatten().count().to_string()).collect()),
+    ];
+
+    let width = rows.iter().flat_map(|(_, values)| values.iter()).map(|value| value.chars().count()).max().unwrap_or(0);
+
+    println!();
+    for (label, values) in rows {
+        let columns : Vec<_> = values.iter().map(|value| format!("{:>width$}", value, width = width)).collect();
+        println!("{:<12}  {}", label, columns.join("  "));
+    }
+}
+
+// names in only one of the two maps get a note, since their damage per cast is missing or partial
+pub fn print_spell_damage(report : &SpellDamageReport)
+{
+    println!();
+    println!("{} damage dealt over {} run(s) and {} turns", report.total_damage, report.runs, report.turns);
+    println!();
+
+    let width = report.spells.iter().map(|spell| spell.name.chars().count()).max().unwrap_or(0).max(5);
+
+    println!("{:<width$}  {:>7}  {:>9}  {:>9}  {:>9}  {:>6}", "spell", "casts", "damage", "per cast", "per turn", "share", width = width);
+    for spell in report.spells.iter() {
+        let per_cast = spell.damage_per_cast.map_or("-".to_string(), |per_cast| format!("{:.2}", per_cast));
+
+        let mut notes = Vec::new();
+        if spell.runs_cast_only > 0 {
+            notes.push(format!("cast without damage in {} run(s)", spell.runs_cast_only));
+        }
+        if spell.runs_damage_only > 0 {
+            notes.push(format!("damage without casts in {} run(s)", spell.runs_damage_only));
+        }
+
+        let line = format!("{:<width$}  {:>7}  {:>9}  {:>9}  {:>9.3}  {:>5.1}%  {}",
+            spell.name, spell.casts, spell.damage, per_cast, spell.casts_per_turn, spell.share_of_damage * 100.0, notes.join(", "), width = width);
+        println!("{}", line.trim_end());
+    }
+}
+
+// each death with its top few sources, then the deadliest enemies over all of them
+pub fn print_deaths(report : &DeathReport, top : usize)
+{
+    println!();
+    println!("{} lost run(s)", report.deaths.len());
+
+    if report.deaths.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("{:>8}  {:>5}  {:<20}  top damage taken in the final realm", "run", "realm", "mode");
+    for death in report.deaths.iter() {
+        let sources : Vec<_> = death.sources.iter().take(top)
+            .map(|source| format!("{} {}", source.name, source.damage))
+            .collect();
+        let sources = if sources.is_empty() { "(no damage recorded)".to_string() } else { sources.join(", ") };
+
+        println!("{:>8}  {:>5}  {:<20}  {}", death.name, death.realm, death.game_mode.to_string(), sources);
+    }
+
+    let width = report.killers.iter().map(|killer| killer.name.chars().count()).max().unwrap_or(0).max(5);
+
+    println!();
+    println!("DEADLIEST ENEMIES (TOP {})", top);
+    println!("{:<width$}  {:>9}  {:>6}  {:>7}", "enemy", "deadliest", "deaths", "damage", width = width);
+    for killer in report.killers.iter().take(top) {
+        println!("{:<width$}  {:>9}  {:>6}  {:>7}", killer.name, killer.deadliest_in, killer.deaths, killer.damage, width = width);
+    }
+}
+
+pub fn print_validation(merged_runs : &MergedRuns, problems : &ReadProblems)
+{
+    let runs = merged_runs.runs.iter().flatten();
+    let files : usize = runs.clone().map(|run| run.realm_stats.iter().flatten().count()).sum();
+
+    println!("read {} stats file(s) in {} run(s)", files, runs.count());
+
+    if problems.failures.is_empty() && problems.warnings.is_empty() {
+        println!("no problems found");
+    } else {
+        println!("{} file(s) or folder(s) failed, {} malformed line(s) skipped", problems.failures.len(), problems.warnings.len());
+    }
+}
+
+pub fn print_merged_runs_info(merged_runs : MergedRuns, report_options : &ReportOptions)
+{
+    println!();
+    println!("====================");
+    println!("MERGED RUNS");
+
+    for summary in summaries(merged_runs, report_options) {
+        print_summary(&summary);
+    }
+}
+
+fn print_summary(summary : &Summary)
+{
+    println!();
+    println!("####################################");
+    println!("{}", summary.title.to_uppercase());
+    println!("####################################");
+
+    println!("runs: {} won, {} lost, {} abandoned, {} unknown / {} total", summary.num_victory, summary.num_defeat, summary.num_abandoned, summary.num_unknown, summary.total_runs);
+
+    for section in summary.sections.iter() {
+        println!("===================================");
+
+        match section {
+            Section::Top(list) => {
+                println!("{}", list.heading.to_uppercase());
+                for row in list.rows.iter() {
+                    if list.rates {
+                        println!("{} - {} ({:.3} per turn, {:.2} per run)", row.name, row.total, row.per_turn, row.per_run);
+                    } else {
+                        println!("{} - {}", row.name, row.total);
+                    }
+                }
+
+                if !list.means.is_empty() {
+                    println!();
+                    for (label, mean) in list.means.iter() {
+                        println!("{}: {}", label, mean);
+                    }
+                }
+            },
+            Section::WinRates { heading, purchases } => {
+                println!("{}", heading.to_uppercase());
+                for outcomes in purchases.iter() {
+                    println!("{} - {:.1}% won - bought in {} runs: {} won, {} lost, {} abandoned",
+                        outcomes.name, outcomes.win_rate() * 100.0, outcomes.runs, outcomes.num_victory, outcomes.num_defeat, outcomes.num_abandoned);
+                }
+            },
+        }
+
+        println!();
+    }
+}
+
+pub fn get_mean_per_turn(map : &HashMap<String, usize>, turns : i64) -> f64
+{
+    let mut running_sum = 0;
+    for (_key, value) in map.iter() {
+        running_sum += value;
+    }
+
+    running_sum as f64 / (turns as f64)
+}
+
+pub fn get_mean_per_run(map : &HashMap<String, usize>, runs : usize) -> f64
+{
+    let running_sum : usize = map.values().sum();
+
+    running_sum as f64 / (runs as f64)
+}
+
+// keys with the biggest values first
+pub fn top_keys(map : &HashMap<String, usize>, n : usize) -> Vec<&String>
+{
+    let mut v : Vec<_> = map.keys().collect();
+
+    // ties by name, so the same saves always print the same way
+    v.sort_by(|a, b| { map.get(*b).unwrap().cmp(map.get(*a).unwrap()).then_with(|| a.cmp(b)) });
+    v.truncate(n);
+
+    v
+}
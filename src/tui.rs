@@ -0,0 +1,229 @@
+//! An interactive terminal view of every run. It only needs a terminal, so it works over SSH too.
+
+use riftwizardstats::{MergedRuns, Outcome, Run, RunSort};
+
+use crate::text::top_keys;
+
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::terminal;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::Frame;
+
+use std::io;
+
+const TABS : [&str; 5] = ["Damage dealt", "Damage taken", "Casts", "Items", "Purchases"];
+const SORTS : [RunSort; 4] = [RunSort::Id, RunSort::Outcome, RunSort::Realms, RunSort::Turns];
+
+struct App<'a>
+{
+    merged_runs : &'a MergedRuns,
+    runs : Vec<&'a Run>,
+    sort : usize,
+    tab : usize,
+    run_list : TableState,
+}
+
+impl<'a> App<'a> {
+    fn new(merged_runs : &'a MergedRuns) -> App<'a>
+    {
+        let mut app = App { merged_runs, runs : Vec::new(), sort : 0, tab : 0, run_list : TableState::default() };
+        app.sort_runs();
+        app.run_list.select(if app.runs.is_empty() { None } else { Some(0) });
+        app
+    }
+
+    fn sort_runs(&mut self)
+    {
+        // keep the same run selected through a re-sort
+        let selected = self.selected_run().map(|run| run.id.clone());
+
+        self.runs = self.merged_runs.sorted_runs(SORTS[self.sort]);
+
+        if let Some(id) = selected {
+            self.run_list.select(self.runs.iter().position(|run| run.id == id));
+        }
+    }
+
+    fn selected_run(&self) -> Option<&'a Run>
+    {
+        self.run_list.selected().and_then(|i| self.runs.get(i).copied())
+    }
+
+    // clamped to the runs there are
+    fn select(&mut self, i : usize)
+    {
+        if !self.runs.is_empty() {
+            self.run_list.select(Some(i.min(self.runs.len() - 1)));
+        }
+    }
+
+    fn move_selection(&mut self, by : isize)
+    {
+        let current = self.run_list.selected().unwrap_or(0);
+        self.select(current.saturating_add_signed(by));
+    }
+}
+
+/// Shows the runs in `merged_runs` until the user quits.
+pub fn run(merged_runs : &MergedRuns) -> io::Result<()>
+{
+    // puts the terminal back on a panic too, and fails rather than panics without one, like over ssh without -t
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            // it may have got as far as raw mode, but there's no screen to put back
+            let _ = terminal::disable_raw_mode();
+            return Err(io::Error::new(err.kind(), format!("the tui needs a terminal: {}", err)));
+        },
+    };
+    let result = event_loop(&mut terminal, App::new(merged_runs));
+    ratatui::restore();
+
+    result
+}
+
+fn event_loop(terminal : &mut DefaultTerminal, mut app : App) -> io::Result<()>
+{
+    loop {
+        terminal.draw(|frame| draw(frame, &mut app))?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+            KeyCode::PageDown => app.move_selection(10),
+            KeyCode::PageUp => app.move_selection(-10),
+            KeyCode::Home | KeyCode::Char('g') => app.select(0),
+            KeyCode::End | KeyCode::Char('G') => app.select(usize::MAX),
+            KeyCode::Right | KeyCode::Tab | KeyCode::Char('l') => app.tab = (app.tab + 1) % TABS.len(),
+            KeyCode::Left | KeyCode::BackTab | KeyCode::Char('h') => app.tab = (app.tab + TABS.len() - 1) % TABS.len(),
+            KeyCode::Char(c @ '1'..='5') => app.tab = c as usize - '1' as usize,
+            KeyCode::Char('s') => {
+                app.sort = (app.sort + 1) % SORTS.len();
+                app.sort_runs();
+            },
+            _ => (),
+        }
+    }
+}
+
+fn draw(frame : &mut Frame, app : &mut App)
+{
+    let [main, help] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .areas(frame.area());
+
+    let [run_list, detail] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(42), Constraint::Min(20)])
+        .areas(main);
+
+    draw_run_list(frame, app, run_list);
+    draw_detail(frame, app, detail);
+
+    frame.render_widget(
+        Paragraph::new("q quit  ↑↓ select  ←→ or 1-5 tab  s sort").style(Style::default().fg(Color::DarkGray)),
+        help);
+}
+
+fn outcome_color(outcome : Outcome) -> Color
+{
+    match outcome {
+        Outcome::Victory => Color::Green,
+        Outcome::Defeat => Color::Red,
+        Outcome::Abandoned => Color::Yellow,
+        Outcome::Unknown => Color::Gray,
+    }
+}
+
+fn draw_run_list(frame : &mut Frame, app : &mut App, area : Rect)
+{
+    let rows : Vec<_> = app.runs.iter()
+        .map(|run| Row::new(vec![
+            Cell::from(run.name.clone()),
+            Cell::from(format!("{:?}", run.outcome)).style(Style::default().fg(outcome_color(run.outcome))),
+            Cell::from(run.realms.to_string()),
+            Cell::from(run.turns_taken_run.to_string()),
+        ]))
+        .collect();
+
+    let title = format!(" {} runs, by {:?} ", app.runs.len(), SORTS[app.sort]).to_lowercase();
+
+    let table = Table::new(rows, [Constraint::Length(8), Constraint::Length(10), Constraint::Length(7), Constraint::Length(7)])
+        .header(Row::new(vec!["run", "outcome", "realms", "turns"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    frame.render_stateful_widget(table, area, &mut app.run_list);
+}
+
+fn draw_detail(frame : &mut Frame, app : &App, area : Rect)
+{
+    let run = match app.selected_run() {
+        Some(run) => run,
+        None => {
+            frame.render_widget(Paragraph::new("no runs found").block(Block::default().borders(Borders::ALL)), area);
+            return;
+        }
+    };
+
+    let [summary, tabs, content] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Length(3), Constraint::Min(3)])
+        .areas(area);
+
+    let lines = vec![
+        Line::from(format!("{:?} - {}", run.outcome, run.game_mode)).style(Style::default().fg(outcome_color(run.outcome))),
+        Line::from(format!("{} realms, {} turns", run.realms, run.turns_taken_run)),
+    ];
+    frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(format!(" run {} ", run.name))), summary);
+
+    frame.render_widget(
+        Tabs::new(TABS.to_vec())
+            .select(app.tab)
+            .block(Block::default().borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)),
+        tabs);
+
+    let block = Block::default().borders(Borders::ALL).title(format!(" {} ", TABS[app.tab]));
+
+    // purchases are a timeline, everything else is a count per name
+    if app.tab == 4 {
+        let rows : Vec<_> = run.purchase_timeline().into_iter()
+            .map(|(realm, names)| Row::new(vec![realm.to_string(), names.join(", ")]))
+            .collect();
+
+        let table = Table::new(rows, [Constraint::Length(6), Constraint::Min(10)])
+            .header(Row::new(vec!["realm", "bought"]).style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(block);
+        frame.render_widget(table, content);
+        return;
+    }
+
+    let map = match app.tab {
+        0 => &run.damage_to_enemies,
+        1 => &run.damage_to_wizard,
+        2 => &run.spell_casts,
+        _ => &run.items_used,
+    };
+    let map = map.clone().unwrap_or_default();
+
+    let rows : Vec<_> = top_keys(&map, map.len()).into_iter()
+        .map(|key| Row::new(vec![key.clone(), map[key].to_string()]))
+        .collect();
+
+    let table = Table::new(rows, [Constraint::Min(20), Constraint::Length(10)])
+        .header(Row::new(vec!["name", "total"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(block);
+    frame.render_widget(table, content);
+}
@@ -1,13 +1,18 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
+use std::time::SystemTime;
 
-use crate::hashmap::HashMapExtensions;
+use serde::Serialize;
 
-#[derive(Default,Debug)]
+use crate::hashmap::{HashMapExtensions, merge_add_nested};
+
+#[derive(Default,Debug,Clone,Serialize)]
 pub struct Realm
 {
     pub realm_number : i64,
     pub outcome : Outcome,
+    pub game_mode : GameMode,
     pub turns_taken_realm : i64,
     pub turns_taken_run : i64,
     pub spell_casts : Option<HashMap<String, usize>>,
@@ -15,22 +20,85 @@ pub struct Realm
     pub damage_to_wizard : Option<HashMap<String, usize>>,
     pub items_used : Option<HashMap<String, usize>>,
     pub purchases : Option<HashSet<String>>,
+    /// sections we don't have a dedicated field for, keyed by their title
+    pub other_sections : Option<HashMap<String, Section>>,
+    /// when the stats file was last written, if the file system knows
+    #[serde(serialize_with = "crate::json::serialize_time")]
+    pub modified : Option<SystemTime>,
+}
+
+/// The contents of a section we don't recognise.
+#[derive(Debug,Clone,Serialize)]
+#[serde(untagged)]
+pub enum Section
+{
+    /// every line was a name and a count, in either `Name: 3` or `3 Name` form
+    Counts(HashMap<String, usize>),
+    Lines(Vec<String>),
+}
+
+impl Section {
+    /// Counts as-is, or each plain line counted once.
+    pub fn to_counts(&self) -> HashMap<String, usize>
+    {
+        match self {
+            Section::Counts(counts) => counts.clone(),
+            Section::Lines(lines) => {
+                let mut counts = HashMap::new();
+                for line in lines.iter() {
+                    *counts.entry(line.clone()).or_insert(0) += 1;
+                }
+                counts
+            },
+        }
+    }
 }
 
-#[derive(Default,Debug)]
+#[derive(Default,Debug,Clone,Serialize)]
 pub struct Run
 {
+    /// unique across save folders, the path of the run's folder, see `load::run_id`
+    pub id : String,
+    /// the run folder's name, which is how the game numbers runs (e.g. `21`), for showing;
+    /// two save folders can both have a run with the same name
+    pub name : String,
     pub realms : i64,
     pub outcome : Outcome,
+    pub game_mode : GameMode,
     pub turns_taken_run : i64,
     pub spell_casts : Option<HashMap<String, usize>>,
     pub damage_to_enemies : Option<HashMap<String, usize>>,
     pub damage_to_wizard : Option<HashMap<String, usize>>,
     pub items_used : Option<HashMap<String, usize>>,
-    pub purchases : Option<HashMap<String, usize>>,
+    /// in the order they were bought, by realm
+    pub purchases : Option<Vec<Purchase>>,
+    pub other_sections : Option<HashMap<String, HashMap<String, usize>>>,
+    /// the realms the run was made from, sorted by realm number
+    pub realm_stats : Option<Vec<Realm>>,
+    /// the latest `Realm::modified` of its realms
+    #[serde(serialize_with = "crate::json::serialize_time")]
+    pub last_played : Option<SystemTime>,
+}
+
+impl Run {
+    /// Purchases grouped by the realm they were bought in, in realm order.
+    /// Realms where nothing was bought are left out.
+    pub fn purchase_timeline(&self) -> Vec<(i64, Vec<&str>)>
+    {
+        let mut timeline : Vec<(i64, Vec<&str>)> = Vec::new();
+        
+        for purchase in self.purchases.iter().flatten() {
+            match timeline.last_mut() {
+                Some((realm, names)) if *realm == purchase.realm => names.push(&purchase.name),
+                _ => timeline.push((purchase.realm, vec![&purchase.name])),
+            }
+        }
+        
+        timeline
+    }
 }
 
-#[derive(Default,Debug)]
+#[derive(Default,Debug,Serialize)]
 pub struct MergedRuns
 {
     pub realms : i64,
@@ -43,14 +111,33 @@ pub struct MergedRuns
     pub damage_to_enemies : Option<HashMap<String, usize>>,
     pub damage_to_wizard : Option<HashMap<String, usize>>,
     pub items_used : Option<HashMap<String, usize>>,
+    /// how many runs made each purchase, split by the run's outcome and the realm it was bought in
+    #[serde(serialize_with = "crate::json::serialize_purchase_counts")]
     pub purchases : Option<HashMap<Purchase, usize>>,
+    pub other_sections : Option<HashMap<String, HashMap<String, usize>>>,
+    /// the same totals again, split up by game mode; `None` inside those
+    #[serde(serialize_with = "crate::json::serialize_by_game_mode")]
+    pub by_game_mode : Option<HashMap<GameMode, MergedRuns>>,
+    /// every run that went into the totals, in the order they were merged; `None` inside `by_game_mode`
+    pub runs : Option<Vec<Run>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunSort
+{
+    Id,
+    Outcome,
+    Realms,
+    Turns,
 }
 
-#[derive(Default,Debug)]
+#[derive(Default,Debug,Clone,PartialEq,Eq,Hash,Serialize)]
 pub struct Purchase
 {
     pub name : String,
+    /// the outcome of the run it was bought in
     pub outcome : Outcome,
+    /// the realm it was bought in
     pub realm : i64,
 }
 
@@ -59,6 +146,54 @@ impl MergedRuns {
     {
         let mut output = self;
         
+        let by_game_mode = output.by_game_mode.get_or_insert_with(HashMap::new);
+        let mode_runs = by_game_mode.remove(&new_run.game_mode).unwrap_or_default();
+        by_game_mode.insert(new_run.game_mode.clone(), mode_runs.merge_totals(&new_run));
+        
+        let mut output = output.merge_totals(&new_run);
+        output.runs.get_or_insert_with(Vec::new).push(new_run);
+        
+        output
+    }
+    
+    /// The run with this id, if there is one.
+    pub fn run(&self, id : &str) -> Option<&Run>
+    {
+        self.runs.iter().flatten().find(|run| run.id == id)
+    }
+    
+    /// Every run, sorted by `sort`. Sorting by id goes by name, numerically when they are numbers as Rift Wizard's are,
+    /// and then by id for runs with the same name in different save folders.
+    /// Sorting by outcome puts victories first, and sorting by realms or turns puts the longest runs first.
+    pub fn sorted_runs(&self, sort : RunSort) -> Vec<&Run>
+    {
+        let mut runs : Vec<_> = self.runs.iter().flatten().collect();
+        
+        let by_id = |a : &Run, b : &Run| {
+            let by_name = match (a.name.parse::<u64>(), b.name.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.name.cmp(&b.name),
+            };
+            by_name.then_with(|| a.id.cmp(&b.id))
+        };
+        
+        runs.sort_by(|a, b| {
+            match sort {
+                RunSort::Id => by_id(a, b),
+                RunSort::Outcome => a.outcome.cmp(&b.outcome).then_with(|| by_id(a, b)),
+                RunSort::Realms => b.realms.cmp(&a.realms).then_with(|| by_id(a, b)),
+                RunSort::Turns => b.turns_taken_run.cmp(&a.turns_taken_run).then_with(|| by_id(a, b)),
+            }
+        });
+        
+        runs
+    }
+    
+    // only the totals are copied out of the run, so the run itself can be kept without cloning it
+    fn merge_totals(self, new_run : &Run) -> MergedRuns
+    {
+        let mut output = self;
+        
         output.realms += new_run.realms;
         
         match new_run.outcome {
@@ -70,24 +205,66 @@ impl MergedRuns {
         
         output.turns_taken += new_run.turns_taken_run;
         
-        output.spell_casts = output.spell_casts.merge_add(new_run.spell_casts);
-        output.damage_to_enemies = output.damage_to_enemies.merge_add(new_run.damage_to_enemies);
-        output.damage_to_wizard = output.damage_to_wizard.merge_add(new_run.damage_to_wizard);
-        output.items_used = output.items_used.merge_add(new_run.items_used);
+        output.spell_casts = output.spell_casts.merge_add(new_run.spell_casts.clone());
+        output.damage_to_enemies = output.damage_to_enemies.merge_add(new_run.damage_to_enemies.clone());
+        output.damage_to_wizard = output.damage_to_wizard.merge_add(new_run.damage_to_wizard.clone());
+        output.items_used = output.items_used.merge_add(new_run.items_used.clone());
+        output.other_sections = merge_add_nested(output.other_sections, new_run.other_sections.clone());
+        
+        if let Some(purchases) = &new_run.purchases {
+            let merged = output.purchases.get_or_insert_with(HashMap::new);
+            for purchase in purchases {
+                *merged.entry(purchase.clone()).or_insert(0) += 1;
+            }
+        }
         
         output
     }
+    
+    /// How many times each spell or skill was bought, whatever the outcome or realm.
+    pub fn purchase_counts(&self) -> HashMap<String, usize>
+    {
+        let mut counts = HashMap::new();
+        
+        for (purchase, count) in self.purchases.iter().flatten() {
+            *counts.entry(purchase.name.clone()).or_insert(0) += count;
+        }
+        
+        counts
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 pub enum Outcome
 {
     Victory,
     Defeat,
+    #[default]
     Unknown,
     Abandoned
 }
 
-impl Default for Outcome {
-    fn default() -> Self { Outcome::Unknown }
-}
\ No newline at end of file
+/// Taken from the extra line trials and weeklies have between `Realm N` and `Outcome:`, which is
+/// the trial's name on its own, or `Weekly Run` and the weekly's identifier.
+#[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(tag = "kind", content = "name")]
+pub enum GameMode
+{
+    #[default]
+    Normal,
+    /// the trial's name
+    Trial(String),
+    /// the weekly's identifier
+    Weekly(String),
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            GameMode::Normal => write!(f, "Normal"),
+            GameMode::Trial(name) => write!(f, "Trial: {}", name),
+            GameMode::Weekly(id) => write!(f, "Weekly: {}", id),
+        }
+    }
+}